@@ -1,58 +1,930 @@
+use crate::actions::request::CancellationToken;
+use crate::actions::request::IncomingRequest;
+use crate::actions::request::Job;
+use crate::actions::templating::render_action;
+use crate::actions::templating::TemplateContext;
+use crate::config::Action;
+use crate::config::InheritEnv;
 use crate::config::Webhook;
+use crate::logging::redact;
 use rusqlite::named_params;
 use rusqlite::Connection;
+use std::fmt::Display;
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::process::CommandExt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Child;
 use std::process::Command;
-use std::sync::Arc;
+use std::process::ExitStatus;
+use std::process::Stdio;
 use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
-pub(crate) fn execute_webhook_actions(
-    webhooks: Vec<Webhook>,
-    conn: Arc<Mutex<Connection>>,
-) -> anyhow::Result<()> {
-    let conn = conn
+const ENV_PREFIX: &str = "PAGOO_";
+
+const SKIPPED_STEP_STATUS: &str = "skipped";
+
+/// Time given to a timed out process to exit after SIGTERM, before it is killed.
+const TERMINATION_GRACE_PERIOD: Duration = Duration::from_secs(5);
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CommandStatus {
+    /// The command exited with a zero exit code.
+    Success,
+    /// The command exited with a non-zero exit code.
+    Failure,
+    /// The command was terminated by a signal it was not sent by Pagoo.
+    Signaled,
+    /// The command could not be started, or its execution could not be followed.
+    SpawnError,
+    TimedOut,
+    Cancelled,
+}
+
+/// The outcome of one execution of a job.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ExecutionResult {
+    pub(crate) status: CommandStatus,
+    pub(crate) exit_code: Option<i32>,
+}
+
+/// A step of the pipeline, its "output" is empty if it was skipped.
+struct StepResult {
+    name: String,
+    executed_command: Option<String>,
+    argv: Option<Vec<String>>,
+    output: Option<CommandOutput>,
+}
+
+struct CommandOutput {
+    status: CommandStatus,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    duration: Duration,
+}
+
+/// Executes the "attempt" (starting at 1) of a job: the steps of its webhook are
+/// executed in order, and stored with a single execution record.
+pub(crate) fn execute_job(
+    job: &Job,
+    attempt: u32,
+    conn: &Mutex<Connection>,
+) -> anyhow::Result<ExecutionResult> {
+    let webhook = &job.webhook;
+    let request = &job.request;
+
+    if job.cancellation.is_cancelled() {
+        info!(
+            "Job \"{}\" of webhook {} was cancelled before it started.",
+            job.id,
+            webhook.label()
+        );
+        return Ok(ExecutionResult {
+            status: CommandStatus::Cancelled,
+            exit_code: None,
+        });
+    }
+
+    let body_file = BodyFile::create(job)?;
+    let environment = get_environment(webhook, request, body_file.path());
+    let stdin = if webhook.pipe_body_to_stdin {
+        Some(request.body.as_bytes())
+    } else {
+        None
+    };
+
+    let started_at = SystemTime::now();
+    let mut context = TemplateContext::new(&webhook.name, request);
+    let mut step_results = Vec::new();
+    let mut failed_step: Option<usize> = None;
+
+    for step in webhook.steps().iter() {
+        if job.cancellation.is_cancelled() || !step.run_if.should_run(failed_step.is_some()) {
+            step_results.push(StepResult {
+                name: step.name.clone(),
+                executed_command: None,
+                argv: None,
+                output: None,
+            });
+            continue;
+        }
+
+        let action = render_action(&step.run, &context);
+        let cmd = build_command(&action, environment.clone());
+        let timeout = action.timeout.map(Duration::from_secs);
+        let output = run_command(cmd, stdin, timeout, &job.cancellation);
+
+        if output.status != CommandStatus::Success && failed_step.is_none() {
+            failed_step = Some(step_results.len());
+        }
+        context.add_step_stdout(&step.name, output_to_string(&output.stdout));
+
+        step_results.push(StepResult {
+            name: step.name.clone(),
+            executed_command: Some(action.to_string()),
+            argv: Some(action.argv()),
+            output: Some(output),
+        });
+    }
+
+    let executed_steps: Vec<&StepResult> = step_results
+        .iter()
+        .filter(|step_result| step_result.output.is_some())
+        .collect();
+    // The result of the execution is the one of the first failed step, or of the last one.
+    let deciding_output = match failed_step {
+        Some(index) => step_results[index].output.as_ref(),
+        None => executed_steps
+            .last()
+            .and_then(|step_result| step_result.output.as_ref()),
+    };
+    let status = match (failed_step, deciding_output) {
+        (Some(_), Some(output)) => output.status,
+        _ if job.cancellation.is_cancelled() => CommandStatus::Cancelled,
+        _ => CommandStatus::Success,
+    };
+    let exit_code = deciding_output.and_then(|output| output.exit_code);
+    let signal = deciding_output.and_then(|output| output.signal);
+
+    let joined = |field: fn(&StepResult) -> String| {
+        executed_steps
+            .iter()
+            .map(|step_result| field(step_result))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    let duration: Duration = executed_steps
+        .iter()
+        .filter_map(|step_result| step_result.output.as_ref())
+        .map(|output| output.duration)
+        .sum();
+
+    let finished_at = SystemTime::now();
+    let argv: Vec<Vec<String>> = executed_steps
+        .iter()
+        .filter_map(|step_result| step_result.argv.as_deref())
+        .map(redact_argv)
+        .collect();
+
+    let execution_id = uuid::Uuid::new_v4().to_string();
+
+    let mut conn = conn
         .lock()
         .expect("Could not retrieve database connection.");
+    let transaction = conn.transaction()?;
 
-    for webhook in webhooks {
-        let mut actions = webhook.actions_to_execute.clone();
-        let all_actions = actions.clone();
+    transaction.execute(
+        "
+        INSERT INTO executions (
+            id,
+            delivery_id,
+            job_id,
+            webhook_name,
+            argv,
+            started_at,
+            finished_at,
+            duration_ms,
+            status,
+            exit_code,
+            signal,
+            stdout,
+            stderr,
+            attempt,
+            max_attempts
+        ) VALUES (
+            :id,
+            :delivery_id,
+            :job_id,
+            :webhook_name,
+            :argv,
+            datetime(:started_at, 'unixepoch'),
+            datetime(:finished_at, 'unixepoch'),
+            :duration_ms,
+            :status,
+            :exit_code,
+            :signal,
+            :stdout,
+            :stderr,
+            :attempt,
+            :max_attempts
+        )
+        ",
+        named_params! {
+            ":id": execution_id,
+            ":delivery_id": request.id,
+            ":job_id": job.id,
+            ":webhook_name": webhook.name,
+            ":argv": serde_json::to_string(&argv)?,
+            ":started_at": unix_timestamp(started_at),
+            ":finished_at": unix_timestamp(finished_at),
+            ":duration_ms": duration.as_millis() as i64,
+            ":status": status.to_string(),
+            ":exit_code": exit_code,
+            ":signal": signal,
+            ":stdout": redact(&joined(|step_result| step_result.stdout())),
+            ":stderr": redact(&joined(|step_result| step_result.stderr())),
+            ":attempt": attempt,
+            ":max_attempts": webhook.retry.as_ref().map_or(1, |retry| retry.max_attempts),
+        },
+    )?;
 
-        let command = actions.remove(0);
-        let mut cmd = Command::new(command);
-        cmd.args(actions.clone());
-        let output = cmd.output()?;
+    for (position, step_result) in step_results.iter().enumerate() {
+        let output = step_result.output.as_ref();
 
-        let status = output.status.code().unwrap();
-        let stdout_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        let stderr_str = String::from_utf8_lossy(&output.stderr).trim().to_string();
-
-        conn.execute(
+        transaction.execute(
             "
-            INSERT INTO logs_webhooks (
-                execution_date,
-                webhook_name,
+            INSERT INTO execution_steps (
+                execution_id,
+                position,
+                step_name,
                 executed_command,
+                argv,
+                command_status,
                 command_exit_code,
+                command_signal,
+                command_duration_ms,
                 command_stdout,
                 command_stderr
             ) VALUES (
-                datetime(),
-                :webhook_name,
+                :execution_id,
+                :position,
+                :step_name,
                 :executed_command,
+                :argv,
+                :command_status,
                 :command_exit_code,
+                :command_signal,
+                :command_duration_ms,
                 :command_stdout,
                 :command_stderr
             )
             ",
             named_params! {
-                ":webhook_name": webhook.name,
-                ":executed_command": all_actions.join(" "),
-                ":command_exit_code": status,
-                ":command_stdout": stdout_str,
-                ":command_stderr": stderr_str,
+                ":execution_id": execution_id,
+                ":position": position,
+                ":step_name": step_result.name,
+                ":executed_command": step_result.executed_command.as_deref().map(redact),
+                ":argv": step_result.argv.as_deref().map(|argv| serde_json::to_string(&redact_argv(argv))).transpose()?,
+                ":command_status": output.map_or(SKIPPED_STEP_STATUS.to_string(), |output| output.status.to_string()),
+                ":command_exit_code": output.and_then(|output| output.exit_code),
+                ":command_signal": output.and_then(|output| output.signal),
+                ":command_duration_ms": output.map(|output| output.duration.as_millis() as i64),
+                ":command_stdout": redact(&step_result.stdout()),
+                ":command_stderr": redact(&step_result.stderr()),
             },
         )?;
     }
 
+    transaction.commit()?;
+
+    Ok(ExecutionResult { status, exit_code })
+}
+
+fn unix_timestamp(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
+
+/// Checks the parts of an action that can only be verified on the host.
+pub(crate) fn validate_action(action: &Action) -> anyhow::Result<()> {
+    if let Some(cwd) = &action.cwd {
+        if !cwd.is_dir() {
+            return Err(anyhow::anyhow!(
+                "Working directory \"{}\" does not exist.",
+                cwd.display()
+            ));
+        }
+    }
+
     Ok(())
 }
+
+fn build_command(action: &Action, pagoo_environment: Vec<(String, String)>) -> Command {
+    let mut argv = action.argv();
+    let program = argv.remove(0);
+
+    let mut cmd = Command::new(program);
+    cmd.args(argv);
+
+    if let Some(cwd) = &action.cwd {
+        cmd.current_dir(cwd);
+    }
+
+    if action.inherit_env != InheritEnv::Toggle(true) {
+        cmd.env_clear();
+        cmd.envs(std::env::vars_os().filter(|(name, _)| {
+            name.to_str()
+                .is_some_and(|name| action.inherit_env.inherits(name))
+        }));
+    }
+
+    cmd.envs(pagoo_environment);
+    cmd.envs(&action.env);
+
+    cmd
+}
+
+/// Never fails: errors are returned as a "SpawnError" status, with the error as stderr.
+fn run_command(
+    mut cmd: Command,
+    stdin: Option<&[u8]>,
+    timeout: Option<Duration>,
+    cancellation: &CancellationToken,
+) -> CommandOutput {
+    let started_at = Instant::now();
+
+    spawn_and_wait(&mut cmd, stdin, timeout, cancellation).unwrap_or_else(|err| {
+        error!(
+            "Could not execute command \"{}\": {}",
+            cmd.get_program().to_string_lossy(),
+            err
+        );
+        CommandOutput {
+            status: CommandStatus::SpawnError,
+            exit_code: None,
+            signal: None,
+            stdout: Vec::new(),
+            stderr: err.to_string().into_bytes(),
+            duration: started_at.elapsed(),
+        }
+    })
+}
+
+fn spawn_and_wait(
+    cmd: &mut Command,
+    stdin: Option<&[u8]>,
+    timeout: Option<Duration>,
+    cancellation: &CancellationToken,
+) -> std::io::Result<CommandOutput> {
+    cmd.stdin(if stdin.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    })
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    // The command gets its own process group, so that it can be
+    // terminated along with the processes it started.
+    .process_group(0);
+
+    let started_at = Instant::now();
+    let mut child = cmd.spawn()?;
+
+    let stdin_writer = stdin.map(|stdin| {
+        // Written from another thread so the process can write to
+        // stdout and stderr while it reads its input.
+        let mut child_stdin = child.stdin.take().expect("Child stdin is piped.");
+        let stdin = stdin.to_vec();
+        std::thread::spawn(move || {
+            // The process may exit without reading its input,
+            // so a "broken pipe" error is not a failure here.
+            let _ = child_stdin.write_all(&stdin);
+        })
+    });
+    let stdout_reader = read_in_background(child.stdout.take().expect("Child stdout is piped."));
+    let stderr_reader = read_in_background(child.stderr.take().expect("Child stderr is piped."));
+
+    let (exit_status, status) = match wait_for_child(&mut child, timeout, cancellation) {
+        Ok(result) => result,
+        Err(err) => {
+            // The process must not keep running if it cannot be followed anymore.
+            signal_process_group(&child, libc::SIGKILL);
+            let _ = child.wait();
+            return Err(err);
+        }
+    };
+    let duration = started_at.elapsed();
+
+    if let Some(stdin_writer) = stdin_writer {
+        let _ = stdin_writer.join();
+    }
+    let stdout = stdout_reader.join().unwrap_or_default();
+    let stderr = stderr_reader.join().unwrap_or_default();
+
+    Ok(CommandOutput {
+        status,
+        exit_code: exit_status.code(),
+        signal: exit_status.signal(),
+        stdout,
+        stderr,
+        duration,
+    })
+}
+
+fn read_in_background<R: Read + Send + 'static>(mut reader: R) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = reader.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+/// Waits for the process to exit. When the timeout is reached or the job is cancelled,
+/// its process group is sent SIGTERM, then SIGKILL if it is still running after a grace period.
+fn wait_for_child(
+    child: &mut Child,
+    timeout: Option<Duration>,
+    cancellation: &CancellationToken,
+) -> std::io::Result<(ExitStatus, CommandStatus)> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    let status = loop {
+        if let Some(exit_status) = child.try_wait()? {
+            let status = if exit_status.success() {
+                CommandStatus::Success
+            } else if exit_status.signal().is_some() {
+                CommandStatus::Signaled
+            } else {
+                CommandStatus::Failure
+            };
+            return Ok((exit_status, status));
+        }
+        if cancellation.is_cancelled() {
+            warn!("Command was cancelled, terminating it.");
+            break CommandStatus::Cancelled;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            warn!(
+                "Command timed out after {} seconds, terminating it.",
+                timeout.unwrap_or_default().as_secs()
+            );
+            break CommandStatus::TimedOut;
+        }
+        std::thread::sleep(WAIT_POLL_INTERVAL);
+    };
+
+    signal_process_group(child, libc::SIGTERM);
+
+    let exit_status = wait_until(child, Instant::now() + TERMINATION_GRACE_PERIOD)?;
+
+    // Also kills the processes that were started by the command
+    // and are still running, even if the command itself has exited.
+    signal_process_group(child, libc::SIGKILL);
+
+    let exit_status = match exit_status {
+        Some(exit_status) => exit_status,
+        None => child.wait()?,
+    };
+
+    Ok((exit_status, status))
+}
+
+fn wait_until(child: &mut Child, deadline: Instant) -> std::io::Result<Option<ExitStatus>> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        std::thread::sleep(WAIT_POLL_INTERVAL);
+    }
+}
+
+fn signal_process_group(child: &Child, signal: libc::c_int) {
+    // The child is the leader of its process group, so the group id is its pid.
+    let result = unsafe { libc::kill(-(child.id() as libc::pid_t), signal) };
+
+    if result != 0 {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ESRCH) {
+            warn!("Could not send signal {} to command: {}", signal, err);
+        }
+    }
+}
+
+fn get_environment(
+    webhook: &Webhook,
+    request: &IncomingRequest,
+    body_file: &Path,
+) -> Vec<(String, String)> {
+    let mut environment = vec![
+        (format!("{}WEBHOOK_NAME", ENV_PREFIX), webhook.name.clone()),
+        (format!("{}REQUEST_ID", ENV_PREFIX), request.id.clone()),
+        (
+            format!("{}BODY_FILE", ENV_PREFIX),
+            body_file.to_string_lossy().to_string(),
+        ),
+    ];
+
+    for (name, value) in &request.headers {
+        let name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        environment.push((format!("{}HEADER_{}", ENV_PREFIX, name), value.clone()));
+    }
+
+    environment
+}
+
+impl Display for CommandStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandStatus::Success => write!(f, "success"),
+            CommandStatus::Failure => write!(f, "failure"),
+            CommandStatus::Signaled => write!(f, "signaled"),
+            CommandStatus::SpawnError => write!(f, "spawn_error"),
+            CommandStatus::TimedOut => write!(f, "timed_out"),
+            CommandStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl StepResult {
+    fn stdout(&self) -> String {
+        self.output
+            .as_ref()
+            .map(|output| output_to_string(&output.stdout))
+            .unwrap_or_default()
+    }
+
+    fn stderr(&self) -> String {
+        self.output
+            .as_ref()
+            .map(|output| output_to_string(&output.stderr))
+            .unwrap_or_default()
+    }
+}
+
+/// Secrets of the config can be given to the actions, they are not stored in the logs.
+fn redact_argv(argv: &[String]) -> Vec<String> {
+    argv.iter().map(|arg| redact(arg).into_owned()).collect()
+}
+
+fn output_to_string(output: &[u8]) -> String {
+    String::from_utf8_lossy(output).trim().to_string()
+}
+
+/// The temporary file containing the request body of a job.
+pub(crate) fn body_file_path(job_id: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("pagoo-{}.body", job_id))
+}
+
+/// A temporary file containing the request body, removed when dropped.
+struct BodyFile {
+    path: PathBuf,
+}
+
+impl BodyFile {
+    fn create(job: &Job) -> anyhow::Result<Self> {
+        let path = body_file_path(&job.id);
+
+        // A job interrupted by a crash is queued again with the same id, and its body file
+        // was not removed. Jobs are claimed before they are executed, so the file is stale.
+        match std::fs::remove_file(&path) {
+            Ok(()) => warn!(
+                "Removed stale body file \"{}\" of an interrupted job.",
+                path.display()
+            ),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)?;
+        file.write_all(job.request.body.as_bytes())?;
+
+        Ok(Self { path })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for BodyFile {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            warn!(
+                "Could not remove temporary body file \"{}\": {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::deliveries;
+    use crate::test_utils;
+    use std::sync::Arc;
+
+    /// The job is stored with its delivery, which its execution is linked to.
+    fn get_job(conn: &Mutex<Connection>, webhook: Webhook) -> Job {
+        let request = IncomingRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            body: r#"{"ref": "refs/heads/main"}"#.to_string(),
+            headers: vec![("x-github-event".to_string(), "push".to_string())],
+            source_ip: None,
+            replay_of: None,
+        };
+
+        let job = Job::new(Arc::new(request), webhook);
+        deliveries::insert_delivery(
+            &mut conn.lock().unwrap(),
+            &job.request,
+            std::slice::from_ref(&job),
+        )
+        .unwrap();
+
+        job
+    }
+
+    fn get_last_stdout(conn: &Mutex<Connection>) -> String {
+        conn.lock()
+            .unwrap()
+            .query_row("SELECT stdout FROM executions", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_environment_and_body_file() {
+        let conn = Mutex::new(test_utils::get_test_database_connection());
+        let webhook = Webhook {
+            name: "env_webhook".to_string(),
+            actions_to_execute: Some(serde_json::from_str(
+                r#"["sh", "-c", "echo $PAGOO_WEBHOOK_NAME $PAGOO_HEADER_X_GITHUB_EVENT; cat $PAGOO_BODY_FILE"]"#,
+            )
+            .unwrap()),
+            ..Default::default()
+        };
+        let job = get_job(&conn, webhook);
+        let body_file = std::env::temp_dir().join(format!("pagoo-{}.body", job.id));
+
+        execute_job(&job, 1, &conn).unwrap();
+
+        assert_eq!(
+            "env_webhook push\n{\"ref\": \"refs/heads/main\"}",
+            get_last_stdout(&conn)
+        );
+        assert!(!body_file.exists());
+    }
+
+    #[test]
+    fn test_body_on_stdin() {
+        let conn = Mutex::new(test_utils::get_test_database_connection());
+        let webhook = Webhook {
+            name: "stdin_webhook".to_string(),
+            actions_to_execute: Some(serde_json::from_str(r#"["cat"]"#).unwrap()),
+            pipe_body_to_stdin: true,
+            ..Default::default()
+        };
+
+        execute_job(&get_job(&conn, webhook), 1, &conn).unwrap();
+
+        assert_eq!(r#"{"ref": "refs/heads/main"}"#, get_last_stdout(&conn));
+    }
+
+    #[test]
+    fn test_action_options() {
+        let conn = Mutex::new(test_utils::get_test_database_connection());
+        std::env::set_var("PAGOO_TEST_INHERITED", "inherited");
+        std::env::set_var("PAGOO_TEST_NOT_INHERITED", "not inherited");
+        let webhook = Webhook {
+            name: "options_webhook".to_string(),
+            actions_to_execute: Some(serde_json::from_str(
+                r#"{
+                    "command": "echo \"$(pwd) $1 $CUSTOM $PAGOO_TEST_INHERITED$PAGOO_TEST_NOT_INHERITED\"",
+                    "args": ["first argument"],
+                    "cwd": "/",
+                    "env": {"CUSTOM": "custom"},
+                    "inherit-env": ["PATH", "PAGOO_TEST_INHERITED"],
+                    "shell": "sh -c"
+                }"#,
+            )
+            .unwrap()),
+            ..Default::default()
+        };
+
+        execute_job(&get_job(&conn, webhook), 1, &conn).unwrap();
+
+        assert_eq!("/ first argument custom inherited", get_last_stdout(&conn));
+    }
+
+    #[test]
+    fn test_validate_action() {
+        let action: Action =
+            serde_json::from_str(r#"{"command": "ls", "cwd": "/some/inexistent/dir"}"#).unwrap();
+        assert!(validate_action(&action).is_err());
+
+        let action: Action = serde_json::from_str(r#"{"command": "ls", "cwd": "/"}"#).unwrap();
+        assert!(validate_action(&action).is_ok());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let conn = Mutex::new(test_utils::get_test_database_connection());
+        let webhook = Webhook {
+            name: "timeout_webhook".to_string(),
+            actions_to_execute: Some(serde_json::from_str(
+                r#"{"command": "sleep 10 & wait; echo finished", "shell": "sh -c", "timeout": 1}"#,
+            )
+            .unwrap()),
+            ..Default::default()
+        };
+
+        let started_at = Instant::now();
+        execute_job(&get_job(&conn, webhook), 1, &conn).unwrap();
+
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+
+        let (status, exit_code, stdout): (String, Option<i32>, String) = conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT status, exit_code, stdout FROM executions",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!("timed_out", status);
+        assert_eq!(None, exit_code);
+        assert_eq!("", stdout);
+    }
+
+    #[test]
+    fn test_cancelled_job_is_not_executed() {
+        let conn = Mutex::new(test_utils::get_test_database_connection());
+        let webhook = Webhook {
+            name: "cancelled_webhook".to_string(),
+            actions_to_execute: Some(serde_json::from_str(r#"["echo", "executed"]"#).unwrap()),
+            ..Default::default()
+        };
+        let job = get_job(&conn, webhook);
+        job.cancellation.cancel();
+
+        execute_job(&job, 1, &conn).unwrap();
+
+        let number_of_executions: i64 = conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM executions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(0, number_of_executions);
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let conn = Mutex::new(test_utils::get_test_database_connection());
+        let webhook = Webhook {
+            name: "pipeline_webhook".to_string(),
+            actions: serde_json::from_str(
+                r#"[
+                    {"name": "version", "run": ["echo", "v1.2.3"]},
+                    {"name": "build", "run": ["sh", "-c", "echo building $1; exit 3", "sh", "{{ steps.version.stdout }}"]},
+                    {"name": "deploy", "run": ["echo", "deploying"]},
+                    {"name": "notify", "run-if": "failure", "run": ["echo", "{{ steps.version.stdout }} failed"]},
+                    {"name": "cleanup", "run-if": "always", "run": ["echo", "cleaned"]}
+                ]"#,
+            )
+            .unwrap(),
+            ..Default::default()
+        };
+
+        let job = get_job(&conn, webhook);
+        let result = execute_job(&job, 1, &conn).unwrap();
+
+        assert_eq!(CommandStatus::Failure, result.status);
+        assert_eq!(Some(3), result.exit_code);
+
+        let conn = conn.lock().unwrap();
+        let (execution_id, delivery_id, job_id, argv, stdout, has_timestamps): (
+            String,
+            String,
+            String,
+            String,
+            String,
+            bool,
+        ) = conn
+            .query_row(
+                "
+                SELECT id, delivery_id, job_id, argv, stdout, started_at <= finished_at
+                FROM executions
+                ",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get(5)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(job.request.id, delivery_id);
+        assert_eq!(job.id, job_id);
+        assert_eq!(
+            r#"[["echo","v1.2.3"],["sh","-c","echo building $1; exit 3","sh","v1.2.3"],["echo","v1.2.3 failed"],["echo","cleaned"]]"#,
+            argv
+        );
+        assert_eq!("v1.2.3\nbuilding v1.2.3\nv1.2.3 failed\ncleaned", stdout);
+        assert!(has_timestamps);
+
+        let mut statement = conn
+            .prepare(
+                "SELECT step_name, command_status FROM execution_steps WHERE execution_id = ? ORDER BY position",
+            )
+            .unwrap();
+        let steps: Vec<(String, String)> = statement
+            .query_map([execution_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|step| step.unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                ("version".to_string(), "success".to_string()),
+                ("build".to_string(), "failure".to_string()),
+                ("deploy".to_string(), "skipped".to_string()),
+                ("notify".to_string(), "success".to_string()),
+                ("cleanup".to_string(), "success".to_string()),
+            ],
+            steps
+        );
+    }
+
+    #[test]
+    fn test_signal_termination() {
+        let conn = Mutex::new(test_utils::get_test_database_connection());
+        let webhook = Webhook {
+            name: "signal_webhook".to_string(),
+            actions_to_execute: Some(
+                serde_json::from_str(r#"["sh", "-c", "kill -9 $$"]"#).unwrap(),
+            ),
+            ..Default::default()
+        };
+
+        let result = execute_job(&get_job(&conn, webhook), 1, &conn).unwrap();
+
+        assert_eq!(CommandStatus::Signaled, result.status);
+        assert_eq!(None, result.exit_code);
+        let signal: Option<i32> = conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT signal FROM executions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(Some(9), signal);
+    }
+
+    #[test]
+    fn test_spawn_error_runs_failure_steps() {
+        let conn = Mutex::new(test_utils::get_test_database_connection());
+        let webhook = Webhook {
+            name: "spawn_error_webhook".to_string(),
+            actions: serde_json::from_str(
+                r#"[
+                    {"name": "missing", "run": ["/some/inexistent/command"]},
+                    {"name": "notify", "run-if": "failure", "run": ["echo", "notified"]}
+                ]"#,
+            )
+            .unwrap(),
+            ..Default::default()
+        };
+
+        let result = execute_job(&get_job(&conn, webhook), 1, &conn).unwrap();
+
+        assert_eq!(CommandStatus::SpawnError, result.status);
+        let conn = conn.lock().unwrap();
+        let mut statement = conn
+            .prepare("SELECT command_status, command_stdout, command_stderr FROM execution_steps ORDER BY position")
+            .unwrap();
+        let steps: Vec<(String, String, String)> = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(|step| step.unwrap())
+            .collect();
+        assert_eq!("spawn_error", steps[0].0);
+        assert!(!steps[0].2.is_empty());
+        assert_eq!(
+            ("success", "notified"),
+            (steps[1].0.as_str(), steps[1].1.as_str())
+        );
+    }
+}
@@ -1,37 +1,364 @@
 use crate::config::Config;
-use crate::config::MatchersStrategy;
+use crate::config::Matcher;
+use crate::config::MatcherExpression;
 use crate::config::Webhook;
-use crate::matchers::headers::match_headers;
-use crate::matchers::json::match_json;
+use crate::matchers::headers::check_header;
+use crate::matchers::json::check_json;
+use crate::matchers::json_path::check_condition;
 use actix_web::http::header::HeaderMap;
+use serde_json::Value;
 
+/// Why a matcher expression matches a request or not, printed by the "test" command.
+/// All the branches of the expression are evaluated.
+#[derive(Debug)]
+pub(crate) enum Explanation {
+    All(bool, Vec<Explanation>),
+    Any(bool, Vec<Explanation>),
+    Not(bool, Box<Explanation>),
+    Matcher(bool, Vec<ConditionResult>),
+}
+
+/// A condition of a matcher, with the reason why it failed, if it did.
+#[derive(Debug)]
+pub(crate) struct ConditionResult {
+    pub(crate) condition: String,
+    pub(crate) failure: Option<String>,
+}
+
+/// The webhooks are matched with their explanation, so that the "test" command
+/// always gives the same result as the server.
 pub(crate) fn from_request_parts(
     config: &Config,
     body_as_string: &str,
     headers: &HeaderMap,
-) -> Result<Vec<Webhook>, anyhow::Error> {
-    let mut matching_webhooks: Vec<Webhook> = Vec::new();
+) -> Vec<Webhook> {
+    let body = parse_body(body_as_string);
+    if let Err(err) = &body {
+        debug!("Deserialization failed, skipping JSON matchers.");
+        debug!("Deserialization error: {}", err);
+    }
 
-    for webhook in &config.webhooks {
-        let strategy = webhook.matchers_strategy.unwrap_or_default();
-        let number_of_matchers = webhook.matchers.len();
-        let mut number_matching = 0;
+    config
+        .webhooks
+        .iter()
+        .filter(|webhook| {
+            let explanation = explain_expression(&webhook.matcher_expression(), &body, headers);
+            if !explanation.matches() {
+                log_failures(webhook, &explanation);
+            }
+            explanation.matches()
+        })
+        .cloned()
+        .collect()
+}
 
-        for matcher in &webhook.matchers {
-            if match_headers(headers, matcher)? || match_json(body_as_string, matcher)? {
-                number_matching += 1;
+/// Explains in the server logs why a webhook did not match, like the "test" command does.
+/// Only the branches that did not match are logged.
+fn log_failures(webhook: &Webhook, explanation: &Explanation) {
+    match explanation {
+        Explanation::All(_, explanations) | Explanation::Any(_, explanations) => {
+            for explanation in explanations.iter().filter(|e| !e.matches()) {
+                log_failures(webhook, explanation);
             }
         }
+        Explanation::Not(_, _) => {
+            debug!(
+                "Webhook {}: the conditions of a \"not\" group matched.",
+                webhook.label()
+            );
+        }
+        Explanation::Matcher(_, conditions) => {
+            for condition in conditions {
+                if let Some(failure) = &condition.failure {
+                    debug!(
+                        "Webhook {}: {} did not match: {}",
+                        webhook.label(),
+                        condition.condition,
+                        failure
+                    );
+                }
+            }
+        }
+    }
+}
 
-        let matched = match strategy {
-            MatchersStrategy::All => number_of_matchers == number_matching,
-            MatchersStrategy::One => number_matching > 0,
-        };
+impl Explanation {
+    pub(crate) fn matches(&self) -> bool {
+        match self {
+            Explanation::All(matches, _)
+            | Explanation::Any(matches, _)
+            | Explanation::Not(matches, _)
+            | Explanation::Matcher(matches, _) => *matches,
+        }
+    }
+}
+
+pub(crate) fn explain(webhook: &Webhook, body_as_string: &str, headers: &HeaderMap) -> Explanation {
+    explain_expression(
+        &webhook.matcher_expression(),
+        &parse_body(body_as_string),
+        headers,
+    )
+}
+
+/// The body is parsed once for all the JSON conditions, which fail if it is not valid JSON.
+fn parse_body(body_as_string: &str) -> Result<Value, String> {
+    serde_json::from_str::<Value>(body_as_string).map_err(|err| err.to_string())
+}
+
+fn explain_expression(
+    expression: &MatcherExpression,
+    body: &Result<Value, String>,
+    headers: &HeaderMap,
+) -> Explanation {
+    match expression {
+        MatcherExpression::All(expressions) => {
+            let explanations: Vec<Explanation> = expressions
+                .iter()
+                .map(|expression| explain_expression(expression, body, headers))
+                .collect();
+            Explanation::All(explanations.iter().all(Explanation::matches), explanations)
+        }
+        MatcherExpression::Any(expressions) => {
+            let explanations: Vec<Explanation> = expressions
+                .iter()
+                .map(|expression| explain_expression(expression, body, headers))
+                .collect();
+            Explanation::Any(explanations.iter().any(Explanation::matches), explanations)
+        }
+        MatcherExpression::Not(expression) => {
+            let explanation = explain_expression(expression, body, headers);
+            Explanation::Not(!explanation.matches(), Box::new(explanation))
+        }
+        MatcherExpression::Matcher(matcher) => explain_matcher(matcher, body, headers),
+    }
+}
+
+fn explain_matcher(
+    matcher: &Matcher,
+    body: &Result<Value, String>,
+    headers: &HeaderMap,
+) -> Explanation {
+    let mut conditions = Vec::new();
+
+    if let Some(match_headers) = &matcher.match_headers {
+        let mut header_names: Vec<&String> = match_headers.keys().collect();
+        header_names.sort();
+
+        for header_name in header_names {
+            let header_value_matcher = &match_headers[header_name];
+            conditions.push(ConditionResult {
+                condition: format!("header \"{}\" {}", header_name, header_value_matcher),
+                failure: check_header(headers, header_name, header_value_matcher).err(),
+            });
+        }
+    }
 
-        if matched {
-            matching_webhooks.push(webhook.clone());
+    if let Some(match_json_body) = &matcher.match_json_body {
+        conditions.push(ConditionResult {
+            condition: format!("JSON body includes {}", match_json_body),
+            failure: match body {
+                Ok(body) => check_json(body, matcher).err(),
+                Err(err) => Some(format!("the body is not valid JSON: {}", err)),
+            },
+        });
+    }
+
+    for condition in matcher.match_json_path.iter().flatten() {
+        conditions.push(ConditionResult {
+            condition: format!("JSON path \"{}\" {}", condition.path, condition.operator),
+            failure: match body {
+                Ok(body) => check_condition(body, condition).err(),
+                Err(err) => Some(format!("the body is not valid JSON: {}", err)),
+            },
+        });
+    }
+
+    let matches = !conditions.is_empty()
+        && conditions
+            .iter()
+            .all(|condition| condition.failure.is_none());
+
+    Explanation::Matcher(matches, conditions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::HeaderName;
+    use actix_web::http::header::HeaderValue;
+
+    fn get_config() -> Config {
+        serde_json::from_str(
+            r#"{
+                "webhooks": [{
+                    "name": "deploy",
+                    "match": {
+                        "any": [
+                            {"all": [
+                                {"match-headers": {"x-github-event": "push"}},
+                                {"match-json-body": {"ref": "refs/heads/main"}}
+                            ]},
+                            {"all": [
+                                {"match-headers": {"x-github-event": "release"}},
+                                {"not": {"match-json-body": {"release": {"prerelease": true}}}}
+                            ]}
+                        ]
+                    },
+                    "actions-to-execute": ["echo", "deploy"]
+                }]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn get_headers(event: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("x-github-event"),
+            HeaderValue::from_static(event),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_expression_with_push() {
+        let config = get_config();
+
+        let matching = from_request_parts(
+            &config,
+            r#"{"ref": "refs/heads/main"}"#,
+            &get_headers("push"),
+        );
+        assert_eq!(1, matching.len());
+
+        let matching = from_request_parts(
+            &config,
+            r#"{"ref": "refs/heads/feature"}"#,
+            &get_headers("push"),
+        );
+        assert!(matching.is_empty());
+    }
+
+    #[test]
+    fn test_expression_with_release() {
+        let config = get_config();
+
+        let matching = from_request_parts(
+            &config,
+            r#"{"action": "published", "release": {"prerelease": false}}"#,
+            &get_headers("release"),
+        );
+        assert_eq!(1, matching.len());
+
+        let matching = from_request_parts(
+            &config,
+            r#"{"action": "published", "release": {"prerelease": true}}"#,
+            &get_headers("release"),
+        );
+        assert!(matching.is_empty());
+    }
+
+    #[test]
+    fn test_explain() {
+        let config = get_config();
+        let webhook = &config.webhooks[0];
+
+        for (body, event) in [
+            (r#"{"ref": "refs/heads/main"}"#, "push"),
+            (r#"{"ref": "refs/heads/feature"}"#, "push"),
+            (r#"{"release": {"prerelease": true}}"#, "release"),
+            (r#"{"release": {"prerelease": false}}"#, "release"),
+            ("not json", "push"),
+        ] {
+            let headers = get_headers(event);
+            let explanation = explain(webhook, body, &headers);
+            let matching = from_request_parts(&config, body, &headers);
+
+            assert_eq!(!matching.is_empty(), explanation.matches(), "{}", body);
+        }
+
+        let explanation = explain(
+            webhook,
+            r#"{"ref": "refs/heads/feature"}"#,
+            &get_headers("push"),
+        );
+        let push_branch = match &explanation {
+            Explanation::Any(false, branches) => &branches[0],
+            _ => panic!("Unexpected explanation: {:?}", explanation),
+        };
+        let json_condition = match push_branch {
+            Explanation::All(false, matchers) => &matchers[1],
+            _ => panic!("Unexpected explanation: {:?}", push_branch),
+        };
+        match json_condition {
+            Explanation::Matcher(false, conditions) => {
+                assert_eq!(
+                    r#"JSON body includes {"ref":"refs/heads/main"}"#,
+                    conditions[0].condition
+                );
+                assert!(conditions[0].failure.is_some());
+            }
+            _ => panic!("Unexpected explanation: {:?}", json_condition),
         }
     }
 
-    Ok(matching_webhooks)
+    #[test]
+    fn test_invalid_header_value() {
+        let mut config = get_config();
+        config.webhooks.extend(
+            serde_json::from_str::<Vec<Webhook>>(
+                r#"[{
+                    "name": "not-ping",
+                    "match": {"not": {"match-headers": {"x-github-event": "ping"}}},
+                    "actions-to-execute": ["echo", "not ping"]
+                }]"#,
+            )
+            .unwrap(),
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("x-github-event"),
+            HeaderValue::from_bytes(b"push\xff").unwrap(),
+        );
+        let body = r#"{"ref": "refs/heads/main"}"#;
+
+        let matching: Vec<String> = from_request_parts(&config, body, &headers)
+            .into_iter()
+            .map(|webhook| webhook.name)
+            .collect();
+        let explained: Vec<String> = config
+            .webhooks
+            .iter()
+            .filter(|webhook| explain(webhook, body, &headers).matches())
+            .map(|webhook| webhook.name.clone())
+            .collect();
+
+        // A value that is not valid text fails the header conditions.
+        assert_eq!(vec!["not-ping"], matching);
+        assert_eq!(matching, explained);
+    }
+
+    #[test]
+    fn test_legacy_matcher_conditions_are_alternatives() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "webhooks": [{
+                    "name": "legacy",
+                    "matchers": [
+                        {"match-headers": {"x-github-event": "push"}, "match-json-body": {"ref": "refs/heads/main"}}
+                    ],
+                    "actions-to-execute": ["echo", "legacy"]
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let matching = from_request_parts(
+            &config,
+            r#"{"ref": "refs/heads/other"}"#,
+            &get_headers("push"),
+        );
+        assert_eq!(1, matching.len());
+    }
 }
@@ -0,0 +1,139 @@
+use crate::actions::matching_webhooks;
+use crate::actions::request::IncomingRequest;
+use crate::actions::request::Job;
+use crate::actions::signature_verification;
+use crate::config::Config;
+use crate::db::deliveries;
+use actix_web::http::header::HeaderMap;
+use actix_web::http::header::HeaderName;
+use actix_web::http::header::HeaderValue;
+use rusqlite::Connection;
+use std::sync::Arc;
+
+/// A stored delivery received again, as a new delivery.
+pub(crate) struct Replay {
+    pub(crate) request: Arc<IncomingRequest>,
+    pub(crate) jobs: Vec<Job>,
+}
+
+/// Matches a stored delivery against the current configuration, and stores the replay
+/// with the jobs to execute. Returns None if the delivery does not exist.
+///
+/// Signatures are verified again, so that a replay cannot trigger webhooks that rejected
+/// the original request. Signatures that expire, like Stripe ones, cannot be replayed.
+pub(crate) fn replay_delivery(
+    config: &Config,
+    conn: &mut Connection,
+    delivery_id: &str,
+) -> anyhow::Result<Option<Replay>> {
+    let delivery = match deliveries::get_delivery(conn, delivery_id)? {
+        Some(delivery) => delivery,
+        None => return Ok(None),
+    };
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in &delivery.headers {
+        match (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            (Ok(name), Ok(value)) => headers.append(name, value),
+            _ => warn!("Header \"{}\" cannot be replayed, it is ignored.", name),
+        }
+    }
+
+    // Like the server, signatures are only verified for the webhooks that matched.
+    let matching_webhooks = matching_webhooks::from_request_parts(config, &delivery.body, &headers);
+    let rejected_webhooks = signature_verification::rejected_webhooks(
+        &matching_webhooks,
+        &headers,
+        delivery.body.as_bytes(),
+    );
+    for rejected in &rejected_webhooks {
+        info!(
+            "Webhook \"{}\" rejected the replay of delivery \"{}\": {}",
+            rejected.webhook_name, delivery.id, rejected.reason
+        );
+    }
+
+    let matching_webhooks = matching_webhooks.into_iter().filter(|webhook| {
+        !rejected_webhooks
+            .iter()
+            .any(|rejected| rejected.webhook_name == webhook.name)
+    });
+
+    let request = Arc::new(IncomingRequest {
+        id: uuid::Uuid::new_v4().to_string(),
+        body: delivery.body,
+        headers: delivery.headers,
+        source_ip: None,
+        replay_of: Some(delivery.id),
+    });
+    let jobs: Vec<Job> = matching_webhooks
+        .map(|webhook| Job::new(Arc::clone(&request), webhook))
+        .collect();
+
+    deliveries::insert_delivery(conn, &request, &jobs)?;
+
+    Ok(Some(Replay { request, jobs }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    fn get_config() -> Config {
+        let mut config = Config::default();
+        config.webhooks = serde_json::from_str(
+            r#"[
+                {
+                    "name": "deploy",
+                    "matchers": [{"match-json-body": {"ref": "refs/heads/main"}}],
+                    "actions-to-execute": ["make", "deploy"]
+                },
+                {
+                    "name": "signed",
+                    "secret": "some-secret",
+                    "matchers": [{"match-json-body": {"ref": "refs/heads/main"}}],
+                    "actions-to-execute": ["make", "release"]
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        config
+    }
+
+    #[test]
+    fn test_replay_delivery() {
+        let mut conn = test_utils::get_test_database_connection();
+        let original = IncomingRequest {
+            id: "original-id".to_string(),
+            body: r#"{"ref": "refs/heads/main"}"#.to_string(),
+            headers: vec![("x-github-event".to_string(), "push".to_string())],
+            source_ip: Some("127.0.0.1".to_string()),
+            replay_of: None,
+        };
+        deliveries::insert_delivery(&mut conn, &original, &[]).unwrap();
+
+        let replay = replay_delivery(&get_config(), &mut conn, "original-id")
+            .unwrap()
+            .unwrap();
+
+        assert_ne!(original.id, replay.request.id);
+        assert_eq!(original.body, replay.request.body);
+        assert_eq!(1, replay.jobs.len());
+        assert_eq!("deploy", replay.jobs[0].webhook.name);
+
+        let stored = deliveries::get_delivery(&conn, &replay.request.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some("original-id".to_string()), stored.replay_of);
+        assert_eq!(vec!["deploy"], stored.matched_webhooks);
+
+        assert!(replay_delivery(&get_config(), &mut conn, "unknown")
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,72 @@
+use crate::config::Webhook;
+use actix_web::http::header::HeaderMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// The parts of the HTTP request that are sent to the actions.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IncomingRequest {
+    pub(crate) id: String,
+    pub(crate) body: String,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) source_ip: Option<String>,
+    /// The delivery this request was replayed from.
+    pub(crate) replay_of: Option<String>,
+}
+
+/// One webhook to execute for a request, run by a worker of the pool.
+#[derive(Debug, Clone)]
+pub(crate) struct Job {
+    pub(crate) id: String,
+    pub(crate) request: Arc<IncomingRequest>,
+    pub(crate) webhook: Webhook,
+    pub(crate) cancellation: CancellationToken,
+}
+
+/// Shared flag used to stop a job that is queued or running.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CancellationToken(Arc<AtomicBool>);
+
+impl IncomingRequest {
+    pub(crate) fn new(body: String, headers: &HeaderMap, source_ip: Option<String>) -> Self {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.as_str().to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).to_string(),
+                )
+            })
+            .collect();
+
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            body,
+            headers,
+            source_ip,
+            replay_of: None,
+        }
+    }
+}
+
+impl Job {
+    pub(crate) fn new(request: Arc<IncomingRequest>, webhook: Webhook) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            request,
+            webhook,
+            cancellation: CancellationToken::default(),
+        }
+    }
+}
+
+impl CancellationToken {
+    pub(crate) fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
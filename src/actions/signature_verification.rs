@@ -0,0 +1,43 @@
+use crate::config::Webhook;
+use crate::signatures::get_verifier;
+use actix_web::http::header::HeaderMap;
+
+pub(crate) struct RejectedWebhook {
+    pub(crate) webhook_name: String,
+    pub(crate) reason: String,
+}
+
+/// Returns the webhooks configured with a signature verification
+/// for which the request could not be verified.
+/// Only the webhooks that matched the request must be given, the signatures
+/// of the other ones are not expected to be valid.
+pub(crate) fn rejected_webhooks(
+    webhooks: &[Webhook],
+    headers: &HeaderMap,
+    body_bytes: &[u8],
+) -> Vec<RejectedWebhook> {
+    let mut rejected_webhooks = Vec::new();
+
+    for webhook in webhooks {
+        let verification = match webhook.signature_verification() {
+            Some(verification) => verification,
+            None => continue,
+        };
+
+        let verifier = get_verifier(&verification);
+
+        if let Err(err) = verifier.verify(headers, body_bytes) {
+            debug!(
+                "Signature verification failed for webhook {}: {}",
+                webhook.label(),
+                err
+            );
+            rejected_webhooks.push(RejectedWebhook {
+                webhook_name: webhook.name.clone(),
+                reason: err.to_string(),
+            });
+        }
+    }
+
+    rejected_webhooks
+}
@@ -0,0 +1,296 @@
+use crate::actions::request::IncomingRequest;
+use crate::config::Action;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Shells that execute the argument following their "-c" option as a script.
+const SHELLS: [&str; 7] = ["sh", "bash", "dash", "zsh", "ksh", "ash", "fish"];
+
+/// Values available in "{{ ... }}" placeholders of the actions arguments.
+pub(crate) struct TemplateContext<'a> {
+    webhook_name: &'a str,
+    request: &'a IncomingRequest,
+    body: Option<Value>,
+    steps_stdout: HashMap<String, String>,
+}
+
+impl<'a> TemplateContext<'a> {
+    pub(crate) fn new(webhook_name: &'a str, request: &'a IncomingRequest) -> Self {
+        Self {
+            webhook_name,
+            request,
+            body: serde_json::from_str(&request.body).ok(),
+            steps_stdout: HashMap::new(),
+        }
+    }
+
+    /// Makes the output of a step available to the next ones.
+    pub(crate) fn add_step_stdout(&mut self, step_name: &str, stdout: String) {
+        self.steps_stdout.insert(step_name.to_string(), stdout);
+    }
+}
+
+/// Renders each argument separately: rendered values are never split
+/// or interpreted by a shell, so they always stay a single argument.
+pub(crate) fn render_arguments(arguments: &[String], context: &TemplateContext) -> Vec<String> {
+    arguments
+        .iter()
+        .map(|argument| render(argument, context))
+        .collect()
+}
+
+/// Renders the arguments and environment values of an action.
+/// The command is never rendered, see "validate_placeholders()".
+pub(crate) fn render_action(action: &Action, context: &TemplateContext) -> Action {
+    Action {
+        args: render_arguments(&action.args, context),
+        env: action
+            .env
+            .iter()
+            .map(|(name, value)| (name.clone(), render(value, context)))
+            .collect(),
+        ..action.clone()
+    }
+}
+
+/// Placeholders are rejected where their values would be interpreted: in the program name,
+/// in the script of a "shell" action, and in the script given to a shell with "-c".
+/// Scripts can use the rendered arguments as "$1", "$2", etc. instead.
+pub(crate) fn validate_placeholders(action: &Action) -> Result<(), String> {
+    if placeholder_regex().is_match(&action.command) {
+        return Err(match action.shell {
+            Some(_) => "Placeholders cannot be used in the script of a \"shell\" action, their values would be interpreted by the shell. Give them in \"args\" and use \"$1\", \"$2\", etc. in the script.".to_string(),
+            None => "Placeholders cannot be used in the \"command\" of an action, only in its arguments.".to_string(),
+        });
+    }
+
+    let program = Path::new(&action.command)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    if action.shell.is_some() || !SHELLS.contains(&program) {
+        return Ok(());
+    }
+
+    let script = action
+        .args
+        .iter()
+        .position(|arg| is_command_option(arg))
+        .and_then(|index| action.args.get(index + 1));
+    match script {
+        Some(script) if placeholder_regex().is_match(script) => Err(format!(
+            "Placeholders cannot be used in the script given to \"{}\", their values would be interpreted by the shell. Give them as the next arguments and use \"$1\", \"$2\", etc. in the script.",
+            program
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The "-c" option of shells, possibly grouped with other options, like "-ec".
+fn is_command_option(arg: &str) -> bool {
+    arg.strip_prefix('-').is_some_and(|options| {
+        !options.starts_with('-')
+            && options.contains('c')
+            && options.chars().all(|c| c.is_ascii_alphabetic())
+    })
+}
+
+fn render(template: &str, context: &TemplateContext) -> String {
+    placeholder_regex()
+        .replace_all(template, |captures: &regex::Captures| {
+            let variable = &captures[1];
+            resolve_variable(variable, context).unwrap_or_else(|| {
+                warn!(
+                    "Template variable \"{}\" has no value for webhook \"{}\", it is replaced with an empty string.",
+                    variable, context.webhook_name
+                );
+                String::new()
+            })
+        })
+        .into_owned()
+}
+
+fn resolve_variable(variable: &str, context: &TemplateContext) -> Option<String> {
+    let (root, path) = match variable.split_once('.') {
+        Some((root, path)) => (root, Some(path)),
+        None => (variable, None),
+    };
+
+    match (root, path) {
+        ("webhook", Some("name")) => Some(context.webhook_name.to_string()),
+        ("body", None) => Some(context.request.body.clone()),
+        ("body", Some(path)) => {
+            let mut value = context.body.as_ref()?;
+            for segment in path.split('.') {
+                value = match value {
+                    Value::Object(map) => map.get(segment)?,
+                    Value::Array(array) => array.get(segment.parse::<usize>().ok()?)?,
+                    _ => return None,
+                };
+            }
+            Some(value_to_argument(value))
+        }
+        ("headers", Some(header_name)) => context
+            .request
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(header_name))
+            .map(|(_, value)| value.clone()),
+        ("steps", Some(path)) => match path.split_once('.') {
+            Some((step_name, "stdout")) => context.steps_stdout.get(step_name).cloned(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn value_to_argument(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static PLACEHOLDER_REGEX: OnceLock<Regex> = OnceLock::new();
+
+    PLACEHOLDER_REGEX.get_or_init(|| Regex::new(r"\{\{\s*([^{}\s]+)\s*\}\}").unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_request(body: &str) -> IncomingRequest {
+        IncomingRequest {
+            body: body.to_string(),
+            headers: vec![("x-github-event".to_string(), "push".to_string())],
+            ..Default::default()
+        }
+    }
+
+    fn render_with(arguments: &[&str], body: &str) -> Vec<String> {
+        let request = get_request(body);
+        let context = TemplateContext::new("deploy", &request);
+        let arguments: Vec<String> = arguments.iter().map(|a| a.to_string()).collect();
+
+        render_arguments(&arguments, &context)
+    }
+
+    #[test]
+    fn test_render_arguments() {
+        let rendered = render_with(
+            &[
+                "deploy.sh",
+                "{{ body.ref }}",
+                "--event={{headers.X-GitHub-Event}}",
+                "{{ webhook.name }}",
+                "{{ body.commits.0.id }}",
+                "{{ body.size }}",
+            ],
+            r#"{"ref": "refs/heads/main", "size": 2, "commits": [{"id": "abc123"}]}"#,
+        );
+
+        assert_eq!(
+            rendered,
+            vec![
+                "deploy.sh",
+                "refs/heads/main",
+                "--event=push",
+                "deploy",
+                "abc123",
+                "2"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_action() {
+        let request = get_request(r#"{"ref": "main"}"#);
+        let context = TemplateContext::new("deploy", &request);
+        let action: Action = serde_json::from_str(
+            r#"{
+                "command": "echo {{ body.ref }} \"$1\"",
+                "args": ["{{ body.ref }}"],
+                "env": {"REF": "{{ body.ref }}"},
+                "shell": "sh -c"
+            }"#,
+        )
+        .unwrap();
+
+        let rendered = render_action(&action, &context);
+
+        assert_eq!("echo {{ body.ref }} \"$1\"", rendered.command);
+        assert_eq!(vec!["main"], rendered.args);
+        assert_eq!(Some(&"main".to_string()), rendered.env.get("REF"));
+    }
+
+    #[test]
+    fn test_placeholders_cannot_be_interpreted() {
+        let validate =
+            |action: &str| validate_placeholders(&serde_json::from_str::<Action>(action).unwrap());
+
+        assert!(validate(r#"["./deploy.sh", "{{ body.ref }}"]"#).is_ok());
+        assert!(validate(r#"["sh", "-c", "deploy \"$1\"", "sh", "{{ body.ref }}"]"#).is_ok());
+        assert!(validate(r#"["{{ body.program }}"]"#)
+            .unwrap_err()
+            .contains("\"command\""));
+        assert!(validate(r#"["sh", "-c", "deploy {{ body.ref }}"]"#)
+            .unwrap_err()
+            .contains("\"sh\""));
+        assert!(validate(r#"["/bin/bash", "-ec", "deploy {{ body.ref }}"]"#)
+            .unwrap_err()
+            .contains("\"bash\""));
+        assert!(
+            validate(r#"{"command": "deploy {{ body.ref }}", "shell": "sh -c"}"#)
+                .unwrap_err()
+                .contains("\"shell\" action")
+        );
+    }
+
+    #[test]
+    fn test_steps_stdout() {
+        let request = get_request("");
+        let mut context = TemplateContext::new("deploy", &request);
+        context.add_step_stdout("build", "v1.2.3".to_string());
+        let arguments = vec![
+            "--version={{ steps.build.stdout }}".to_string(),
+            "{{ steps.unknown.stdout }}".to_string(),
+            "{{ steps.build }}".to_string(),
+        ];
+
+        let rendered = render_arguments(&arguments, &context);
+
+        assert_eq!(vec!["--version=v1.2.3", "", ""], rendered);
+    }
+
+    #[test]
+    fn test_values_are_not_split_or_interpreted() {
+        let rendered = render_with(
+            &["{{ body.ref }}"],
+            r#"{"ref": "main; rm -rf / $(whoami) {{ webhook.name }}"}"#,
+        );
+
+        assert_eq!(
+            rendered,
+            vec!["main; rm -rf / $(whoami) {{ webhook.name }}"]
+        );
+    }
+
+    #[test]
+    fn test_missing_values_are_empty() {
+        let rendered = render_with(
+            &[
+                "{{ body.unknown }}",
+                "{{ headers.x-unknown }}",
+                "{{ unknown }}",
+            ],
+            "not json",
+        );
+
+        assert_eq!(rendered, vec!["", "", ""]);
+    }
+}
@@ -1,22 +1,40 @@
 use std::fs::File;
+use crate::config::ConfigFormat;
 use crate::APPLICATION_NAME;
 use crate::CommandHandler;
 use std::io::Write;
 use std::process::ExitCode;
+use clap::Arg;
 use clap::ArgMatches;
 use clap::Command as ClapCommand;
 
-const FILE_SAMPLE: &str = include_str!("../../samples/init_sample.json");
+const JSON_SAMPLE: &str = include_str!("../../samples/init_sample.json");
+const JSON5_SAMPLE: &str = include_str!("../../samples/init_sample.json5");
+const YAML_SAMPLE: &str = include_str!("../../samples/init_sample.yaml");
+const TOML_SAMPLE: &str = include_str!("../../samples/init_sample.toml");
 
 pub(crate) fn get_command() -> CommandHandler {
     CommandHandler::new(
-        ClapCommand::new("init").about("Creates a default configuration file"),
+        ClapCommand::new("init")
+            .about("Creates a default configuration file")
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .num_args(1)
+                    .value_parser(ConfigFormat::ALL.map(|format| format.name()))
+                    .default_value(ConfigFormat::Json.name())
+                    .help("The format of the configuration file"),
+            ),
         Box::new(execute),
     )
 }
 
-fn execute(_config_file_value: Option<&str>, _args: &ArgMatches) -> Option<ExitCode> {
-    let config_file_name = format!(".{}.json", APPLICATION_NAME.to_ascii_lowercase());
+fn execute(_config_file_value: Option<&str>, args: &ArgMatches) -> Option<ExitCode> {
+    let format = args
+        .get_one::<String>("format")
+        .and_then(|name| ConfigFormat::from_name(name))
+        .unwrap_or_default();
+    let config_file_name = format!(".{}.{}", APPLICATION_NAME.to_ascii_lowercase(), format.extensions()[0]);
     let config_file_path = std::env::current_dir().unwrap().join(&config_file_name);
 
     if config_file_path.exists() {
@@ -27,7 +45,7 @@ fn execute(_config_file_value: Option<&str>, _args: &ArgMatches) -> Option<ExitC
 
     let file = File::create(&config_file_path);
 
-    if let Err(err) = write!(&mut file.unwrap(), "{}", FILE_SAMPLE) {
+    if let Err(err) = write!(&mut file.unwrap(), "{}", file_sample(format)) {
         error!("Could not write to file. Error: {}", err.to_string());
 
         return Some(ExitCode::FAILURE);
@@ -37,3 +55,12 @@ fn execute(_config_file_value: Option<&str>, _args: &ArgMatches) -> Option<ExitC
 
     Some(ExitCode::SUCCESS)
 }
+
+fn file_sample(format: ConfigFormat) -> &'static str {
+    match format {
+        ConfigFormat::Json => JSON_SAMPLE,
+        ConfigFormat::Json5 => JSON5_SAMPLE,
+        ConfigFormat::Yaml => YAML_SAMPLE,
+        ConfigFormat::Toml => TOML_SAMPLE,
+    }
+}
@@ -0,0 +1,318 @@
+use crate::config;
+use crate::db::executions;
+use crate::db::executions::ExecutionDetails;
+use crate::db::executions::ExecutionFilter;
+use crate::db::executions::ExecutionSummary;
+use crate::db::get_database_connection;
+use crate::CommandHandler;
+use clap::builder::PossibleValuesParser;
+use clap::value_parser;
+use clap::Arg;
+use clap::ArgAction;
+use clap::ArgMatches;
+use clap::Command as ClapCommand;
+use rusqlite::Connection;
+use std::process::ExitCode;
+use std::time::Duration;
+
+const DEFAULT_LIMIT: &str = "20";
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const STATUSES: [&str; 7] = [
+    executions::FAILED_STATUS_FILTER,
+    "success",
+    "failure",
+    "signaled",
+    "spawn_error",
+    "timed_out",
+    "cancelled",
+];
+
+pub(crate) fn get_command() -> CommandHandler {
+    CommandHandler::new(
+        ClapCommand::new("logs")
+            .about("Lists the executions of the webhooks' actions")
+            .args_conflicts_with_subcommands(true)
+            .arg(
+                Arg::new("webhook")
+                    .long("webhook")
+                    .num_args(1)
+                    .help("Only list executions of this webhook"),
+            )
+            .arg(
+                Arg::new("since")
+                    .long("since")
+                    .num_args(1)
+                    .help("Only list executions started after this date (\"2024-01-31 12:00:00\") or duration (\"30m\", \"2h\", \"7d\")"),
+            )
+            .arg(
+                Arg::new("status")
+                    .long("status")
+                    .action(ArgAction::Append)
+                    .value_parser(PossibleValuesParser::new(STATUSES))
+                    .help("Only list executions with this status, \"failed\" matches all failing statuses. Can be repeated"),
+            )
+            .arg(
+                Arg::new("limit")
+                    .long("limit")
+                    .num_args(1)
+                    .value_parser(value_parser!(usize))
+                    .default_value(DEFAULT_LIMIT)
+                    .help("Maximum number of executions to list"),
+            )
+            .arg(
+                Arg::new("follow")
+                    .short('f')
+                    .long("follow")
+                    .num_args(0)
+                    .help("Keep listing new executions as they are logged"),
+            )
+            .arg(format_arg())
+            .subcommand(
+                ClapCommand::new("show")
+                    .about("Shows one execution with its steps and output")
+                    .arg(Arg::new("id").required(true).help("The execution id"))
+                    .arg(format_arg()),
+            ),
+        Box::new(execute),
+    )
+}
+
+fn format_arg() -> Arg {
+    Arg::new("format")
+        .long("format")
+        .num_args(1)
+        .value_parser(["table", "json"])
+        .default_value("table")
+        .help("The output format")
+}
+
+fn execute(config_file_value: Option<&str>, args: &ArgMatches) -> Option<ExitCode> {
+    let result = get_connection(config_file_value).and_then(|conn| match args.subcommand() {
+        Some(("show", show_args)) => show(&conn, show_args),
+        _ => list(&conn, args),
+    });
+
+    match result {
+        Ok(_) => Some(ExitCode::SUCCESS),
+        Err(e) => {
+            error!("{}", e);
+            Some(ExitCode::FAILURE)
+        }
+    }
+}
+
+fn get_connection(config_file_value: Option<&str>) -> anyhow::Result<Connection> {
+    get_database_connection(config::get_database_file(config_file_value)?)
+}
+
+fn is_json(args: &ArgMatches) -> bool {
+    args.get_one::<String>("format").map(|s| s.as_str()) == Some("json")
+}
+
+fn list(conn: &Connection, args: &ArgMatches) -> anyhow::Result<()> {
+    let since = args
+        .get_one::<String>("since")
+        .map(|since| executions::resolve_since(conn, since))
+        .transpose()?;
+    let statuses = args
+        .get_many::<String>("status")
+        .unwrap_or_default()
+        .flat_map(|status| executions::expand_status_filter(status))
+        .collect();
+    let json = is_json(args);
+    let follow = args.get_flag("follow");
+
+    let mut filter = ExecutionFilter {
+        webhook_name: args.get_one::<String>("webhook").cloned(),
+        since,
+        statuses,
+        limit: args.get_one::<usize>("limit").copied(),
+        ..Default::default()
+    };
+
+    // Executions are displayed in chronological order, so that new ones can be appended.
+    let mut found = executions::find_executions(conn, &filter)?;
+    found.reverse();
+
+    if json && !follow {
+        println!("{}", serde_json::to_string_pretty(&found)?);
+        return Ok(());
+    }
+
+    if !json {
+        println!("{}", table_header());
+    }
+    print_executions(&found, json)?;
+
+    if !follow {
+        return Ok(());
+    }
+
+    filter.limit = None;
+    let mut last_rowid = found.last().map_or(0, |execution| execution.rowid);
+
+    loop {
+        std::thread::sleep(FOLLOW_POLL_INTERVAL);
+
+        filter.after_rowid = Some(last_rowid);
+        let mut found = executions::find_executions(conn, &filter)?;
+        found.reverse();
+
+        print_executions(&found, json)?;
+        if let Some(execution) = found.last() {
+            last_rowid = execution.rowid;
+        }
+    }
+}
+
+/// When following executions in JSON, one object is printed per line.
+fn print_executions(executions: &[ExecutionSummary], json: bool) -> anyhow::Result<()> {
+    for execution in executions {
+        if json {
+            println!("{}", serde_json::to_string(execution)?);
+        } else {
+            println!("{}", table_row(execution));
+        }
+    }
+
+    Ok(())
+}
+
+fn table_header() -> String {
+    format!(
+        "{:<36}  {:<19}  {:<20}  {:<11}  {:>4}  {:>9}  {:>7}",
+        "ID", "STARTED AT", "WEBHOOK", "STATUS", "EXIT", "DURATION", "ATTEMPT"
+    )
+}
+
+fn table_row(execution: &ExecutionSummary) -> String {
+    format!(
+        "{:<36}  {:<19}  {:<20}  {:<11}  {:>4}  {:>9}  {:>7}",
+        execution.id,
+        execution.started_at,
+        execution.webhook_name,
+        execution.status,
+        execution
+            .exit_code
+            .map_or_else(|| "-".to_string(), |code| code.to_string()),
+        format_duration(execution.duration_ms),
+        format!("{}/{}", execution.attempt, execution.max_attempts),
+    )
+}
+
+fn format_duration(duration_ms: Option<i64>) -> String {
+    match duration_ms {
+        None => "-".to_string(),
+        Some(ms) if ms < 1000 => format!("{}ms", ms),
+        Some(ms) => format!("{:.2}s", ms as f64 / 1000.0),
+    }
+}
+
+fn show(conn: &Connection, args: &ArgMatches) -> anyhow::Result<()> {
+    let id = args.get_one::<String>("id").unwrap();
+
+    let execution = executions::get_execution(conn, id)?
+        .ok_or_else(|| anyhow::anyhow!("Execution \"{}\" does not exist.", id))?;
+
+    if is_json(args) {
+        println!("{}", serde_json::to_string_pretty(&execution)?);
+    } else {
+        print!("{}", format_details(&execution));
+    }
+
+    Ok(())
+}
+
+fn format_details(execution: &ExecutionDetails) -> String {
+    let summary = &execution.summary;
+    let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+    let optional_number =
+        |value: Option<i32>| value.map_or_else(|| "-".to_string(), |value| value.to_string());
+
+    let mut details = format!(
+        "Execution:   {}\nWebhook:     {}\nDelivery:    {}\nJob:         {}\nStatus:      {}\nExit code:   {}\nSignal:      {}\nStarted at:  {}\nFinished at: {}\nDuration:    {}\nAttempt:     {}/{}\n",
+        summary.id,
+        summary.webhook_name,
+        optional(&summary.delivery_id),
+        optional(&summary.job_id),
+        summary.status,
+        optional_number(summary.exit_code),
+        optional_number(summary.signal),
+        summary.started_at,
+        summary.finished_at,
+        format_duration(summary.duration_ms),
+        summary.attempt,
+        summary.max_attempts,
+    );
+
+    if execution.steps.is_empty() {
+        for argv in &execution.argv {
+            details.push_str(&format!("Command:     {}\n", argv.join(" ")));
+        }
+    } else {
+        details.push_str("\nSteps:\n");
+        for step in &execution.steps {
+            details.push_str(&format!(
+                "  {:<20}  {:<11}  {:>4}  {:>9}  {}\n",
+                step.name,
+                step.status,
+                optional_number(step.exit_code),
+                format_duration(step.duration_ms),
+                step.argv
+                    .as_ref()
+                    .map(|argv| argv.join(" "))
+                    .unwrap_or_default(),
+            ));
+        }
+    }
+
+    details.push_str(&format!(
+        "\nStdout:\n{}\n\nStderr:\n{}\n",
+        execution.stdout, execution.stderr
+    ));
+
+    details
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!("-", format_duration(None));
+        assert_eq!("250ms", format_duration(Some(250)));
+        assert_eq!("1.50s", format_duration(Some(1500)));
+    }
+
+    #[test]
+    fn test_cli() {
+        let command = get_command().command_definition;
+        command.clone().debug_assert();
+
+        let matches = command
+            .clone()
+            .try_get_matches_from(["logs", "--status", "failed", "--status", "timed_out"])
+            .unwrap();
+        assert_eq!(
+            vec!["failed", "timed_out"],
+            matches
+                .get_many::<String>("status")
+                .unwrap()
+                .collect::<Vec<_>>()
+        );
+
+        assert!(command
+            .clone()
+            .try_get_matches_from(["logs", "--status", "unknown"])
+            .is_err());
+
+        let matches = command
+            .try_get_matches_from(["logs", "show", "some-id", "--format", "json"])
+            .unwrap();
+        let (name, show_args) = matches.subcommand().unwrap();
+        assert_eq!("show", name);
+        assert!(is_json(show_args));
+    }
+}
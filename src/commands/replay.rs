@@ -0,0 +1,102 @@
+use crate::actions::replay;
+use crate::config;
+use crate::db::get_database_connection;
+use crate::db::is_server_running;
+use crate::db::lock_workers;
+use crate::serve::workers;
+use crate::CommandHandler;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::Command as ClapCommand;
+use std::process::ExitCode;
+
+pub(crate) fn get_command() -> CommandHandler {
+    CommandHandler::new(
+        ClapCommand::new("replay")
+            .about(
+                "Executes the actions of a stored delivery again, with the current configuration",
+            )
+            .arg(
+                Arg::new("delivery-id")
+                    .required(true)
+                    .help("The id of the delivery to replay"),
+            )
+            .arg(
+                Arg::new("execute")
+                    .long("execute")
+                    .num_args(0)
+                    .help("Execute the actions in this process and wait for them, instead of queuing them for the server. Refused while a server is running"),
+            ),
+        Box::new(execute),
+    )
+}
+
+fn execute(config_file_value: Option<&str>, args: &ArgMatches) -> Option<ExitCode> {
+    let delivery_id = args.get_one::<String>("delivery-id").unwrap();
+
+    match replay_delivery(config_file_value, delivery_id, args.get_flag("execute")) {
+        Ok(true) => Some(ExitCode::SUCCESS),
+        Ok(false) => Some(ExitCode::FAILURE),
+        Err(e) => {
+            error!("{}", e);
+            Some(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Queues the jobs of the replay for the server. With "execute", returns whether
+/// all the jobs of the replay succeeded.
+fn replay_delivery(
+    config_file_value: Option<&str>,
+    delivery_id: &str,
+    execute: bool,
+) -> anyhow::Result<bool> {
+    let config = config::get_config(config_file_value)?;
+
+    // The lock prevents a server from starting while the jobs are executed here,
+    // which would execute them without their concurrency policies too.
+    let _workers_lock = match execute {
+        true => Some(lock_workers(config.database_file.clone())?.ok_or_else(|| {
+            anyhow::anyhow!("A server is running, replay the delivery without \"--execute\" to queue its actions on the server.")
+        })?),
+        false => None,
+    };
+
+    let mut conn = get_database_connection(config.database_file.clone())?;
+
+    let replay = replay::replay_delivery(&config, &mut conn, delivery_id)?
+        .ok_or_else(|| anyhow::anyhow!("Delivery \"{}\" does not exist.", delivery_id))?;
+
+    if replay.jobs.is_empty() {
+        warn!(
+            "Delivery \"{}\" matched no webhook, it was stored as delivery \"{}\".",
+            delivery_id, replay.request.id
+        );
+        return Ok(false);
+    }
+
+    if !execute {
+        info!(
+            "Queued {} job(s) of delivery \"{}\" as delivery \"{}\".",
+            replay.jobs.len(),
+            delivery_id,
+            replay.request.id
+        );
+        if !is_server_running(config.database_file.clone())? {
+            warn!("No server is running, the jobs will be executed when it starts. Use \"--execute\" to execute them now.");
+        }
+        return Ok(true);
+    }
+
+    info!(
+        "Replaying delivery \"{}\" as delivery \"{}\".",
+        delivery_id, replay.request.id
+    );
+
+    workers::run_delivery_jobs(
+        conn,
+        &replay.request.id,
+        replay.jobs,
+        config.workers.unwrap_or(workers::DEFAULT_WORKERS),
+    )
+}
@@ -0,0 +1,291 @@
+use crate::actions::matching_webhooks;
+use crate::actions::matching_webhooks::Explanation;
+use crate::actions::request::IncomingRequest;
+use crate::actions::request::Job;
+use crate::actions::signature_verification;
+use crate::config;
+use crate::config::Config;
+use crate::db::deliveries;
+use crate::db::get_database_connection;
+use crate::db::lock_workers;
+use crate::serve::workers;
+use crate::CommandHandler;
+use actix_web::http::header::HeaderMap;
+use actix_web::http::header::HeaderName;
+use actix_web::http::header::HeaderValue;
+use clap::Arg;
+use clap::ArgAction;
+use clap::ArgMatches;
+use clap::Command as ClapCommand;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+pub(crate) fn get_command() -> CommandHandler {
+    CommandHandler::new(
+        ClapCommand::new("test")
+            .about("Shows which webhooks match a request, and why, without starting the server")
+            .arg(
+                Arg::new("body")
+                    .long("body")
+                    .num_args(1)
+                    .conflicts_with("request")
+                    .help("A file containing the request body"),
+            )
+            .arg(
+                Arg::new("header")
+                    .short('H')
+                    .long("header")
+                    .action(ArgAction::Append)
+                    .help("A request header, like \"X-GitHub-Event: push\". Can be repeated"),
+            )
+            .arg(
+                Arg::new("request")
+                    .long("request")
+                    .num_args(1)
+                    .help("A file containing a raw HTTP request, with its headers and body"),
+            )
+            .arg(
+                Arg::new("execute")
+                    .long("execute")
+                    .num_args(0)
+                    .help("Execute the actions of the matching webhooks, or queue them when a server is running"),
+            ),
+        Box::new(execute),
+    )
+}
+
+fn execute(config_file_value: Option<&str>, args: &ArgMatches) -> Option<ExitCode> {
+    match test_request(config_file_value, args) {
+        Ok(true) => Some(ExitCode::SUCCESS),
+        Ok(false) => Some(ExitCode::FAILURE),
+        Err(e) => {
+            error!("{}", e);
+            Some(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Returns whether a webhook matched, or whether all the actions succeeded
+/// when they are executed by the command with "--execute".
+fn test_request(config_file_value: Option<&str>, args: &ArgMatches) -> anyhow::Result<bool> {
+    let config = config::get_config(config_file_value)?;
+
+    let (mut header_lines, body) = match args.get_one::<String>("request") {
+        Some(request_file) => parse_http_request(&read_file(request_file)?),
+        None => (
+            Vec::new(),
+            args.get_one::<String>("body")
+                .map(|body_file| read_file(body_file))
+                .transpose()?
+                .unwrap_or_default(),
+        ),
+    };
+    header_lines.extend(
+        args.get_many::<String>("header")
+            .unwrap_or_default()
+            .cloned(),
+    );
+
+    let headers = parse_headers(&header_lines)?;
+
+    let matching_webhooks = explain_matching(&config, &body, &headers);
+
+    if matching_webhooks.is_empty() {
+        println!("The request matched no webhook.");
+        return Ok(false);
+    }
+
+    if !args.get_flag("execute") {
+        return Ok(true);
+    }
+
+    let request = Arc::new(IncomingRequest::new(body, &headers, None));
+    let jobs: Vec<Job> = config
+        .webhooks
+        .iter()
+        .filter(|webhook| matching_webhooks.contains(&webhook.name))
+        .map(|webhook| Job::new(Arc::clone(&request), webhook.clone()))
+        .collect();
+
+    // Jobs are executed by the running server, which applies their concurrency policies.
+    let workers_lock = lock_workers(config.database_file.clone())?;
+
+    let mut conn = get_database_connection(config.database_file.clone())?;
+    deliveries::insert_delivery(&mut conn, &request, &jobs)?;
+
+    if workers_lock.is_none() {
+        info!(
+            "Queued the actions on the running server as delivery \"{}\".",
+            request.id
+        );
+        return Ok(true);
+    }
+
+    info!("Executing the actions as delivery \"{}\".", request.id);
+
+    workers::run_delivery_jobs(
+        conn,
+        &request.id,
+        jobs,
+        config.workers.unwrap_or(workers::DEFAULT_WORKERS),
+    )
+}
+
+/// Prints why each webhook matched or not, and returns the names of the matching webhooks.
+fn explain_matching(config: &Config, body: &str, headers: &HeaderMap) -> Vec<String> {
+    let rejected_webhooks =
+        signature_verification::rejected_webhooks(&config.webhooks, headers, body.as_bytes());
+    let mut matching_webhooks = Vec::new();
+
+    for webhook in &config.webhooks {
+        let explanation = matching_webhooks::explain(webhook, body, headers);
+        let rejection = rejected_webhooks
+            .iter()
+            .find(|rejected| rejected.webhook_name == webhook.name);
+        let matches = explanation.matches() && rejection.is_none();
+
+        println!(
+            "Webhook \"{}\": {}",
+            webhook.name,
+            if matches { "MATCHED" } else { "NOT MATCHED" }
+        );
+        if webhook.signature_verification().is_some() {
+            match rejection {
+                Some(rejected) => println!("  FAIL signature: {}", rejected.reason),
+                None => println!("  PASS signature"),
+            }
+        }
+        print!("{}", format_explanation(&explanation, 1));
+        println!();
+
+        if matches {
+            matching_webhooks.push(webhook.name.clone());
+        }
+    }
+
+    matching_webhooks
+}
+
+fn format_explanation(explanation: &Explanation, depth: usize) -> String {
+    let indent = "  ".repeat(depth);
+    let status = |matches: bool| if matches { "PASS" } else { "FAIL" };
+
+    match explanation {
+        Explanation::All(matches, explanations) | Explanation::Any(matches, explanations) => {
+            let name = match explanation {
+                Explanation::All(..) => "all of",
+                _ => "any of",
+            };
+            let mut lines = format!("{}{} {}:\n", indent, status(*matches), name);
+            for explanation in explanations {
+                lines.push_str(&format_explanation(explanation, depth + 1));
+            }
+            lines
+        }
+        Explanation::Not(matches, explanation) => format!(
+            "{}{} not:\n{}",
+            indent,
+            status(*matches),
+            format_explanation(explanation, depth + 1)
+        ),
+        Explanation::Matcher(_, conditions) if conditions.is_empty() => {
+            format!("{}FAIL matcher has no conditions\n", indent)
+        }
+        Explanation::Matcher(_, conditions) => conditions
+            .iter()
+            .map(|condition| match &condition.failure {
+                None => format!("{}PASS {}\n", indent, condition.condition),
+                Some(failure) => format!("{}FAIL {}: {}\n", indent, condition.condition, failure),
+            })
+            .collect(),
+    }
+}
+
+fn read_file(path: &str) -> anyhow::Result<String> {
+    std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("Could not read file \"{}\": {}", path, err))
+}
+
+/// Splits a raw HTTP request in its header lines and body. The request line is optional.
+fn parse_http_request(request: &str) -> (Vec<String>, String) {
+    let (head, body) = match request.find("\r\n\r\n") {
+        Some(index) => (&request[..index], &request[index + 4..]),
+        None => match request.find("\n\n") {
+            Some(index) => (&request[..index], &request[index + 2..]),
+            None => (request, ""),
+        },
+    };
+
+    let header_lines = head
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .filter(|(index, line)| !(*index == 0 && line.contains(" HTTP/")))
+        .map(|(_, line)| line.to_string())
+        .collect();
+
+    (header_lines, body.to_string())
+}
+
+fn parse_headers(header_lines: &[String]) -> anyhow::Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+
+    for line in header_lines {
+        let (name, value) = line.split_once(':').ok_or_else(|| {
+            anyhow::anyhow!("Invalid header \"{}\", expected \"Name: value\".", line)
+        })?;
+
+        headers.append(
+            HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|err| anyhow::anyhow!("Invalid header name \"{}\": {}", name, err))?,
+            HeaderValue::from_str(value.trim())
+                .map_err(|err| anyhow::anyhow!("Invalid header value \"{}\": {}", value, err))?,
+        );
+    }
+
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_http_request() {
+        let (header_lines, body) = parse_http_request(
+            "POST /webhook HTTP/1.1\r\nHost: localhost\r\nX-GitHub-Event: push\r\n\r\n{\"ref\": \"main\"}",
+        );
+
+        assert_eq!(
+            vec!["Host: localhost", "X-GitHub-Event: push"],
+            header_lines
+        );
+        assert_eq!("{\"ref\": \"main\"}", body);
+
+        let headers = parse_headers(&header_lines).unwrap();
+        assert_eq!("push", headers.get("x-github-event").unwrap());
+
+        assert!(parse_headers(&["X-GitHub-Event push".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_format_explanation() {
+        let webhook = serde_json::from_str(
+            r#"{
+                "name": "deploy",
+                "match": {"not": {"match-headers": {"x-github-event": "ping"}, "match-json-body": {"ref": "main"}}},
+                "actions-to-execute": ["true"]
+            }"#,
+        )
+        .unwrap();
+        let headers = parse_headers(&["X-GitHub-Event: ping".to_string()]).unwrap();
+
+        let explanation = matching_webhooks::explain(&webhook, r#"{"ref": "dev"}"#, &headers);
+
+        assert_eq!(
+            "  PASS not:\n    PASS header \"x-github-event\" is \"ping\"\n    FAIL JSON body includes {\"ref\":\"main\"}: \".ref\" is \"dev\" but \"main\" was expected\n",
+            format_explanation(&explanation, 1)
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use crate::config;
+use crate::CommandHandler;
+use clap::ArgMatches;
+use clap::Command as ClapCommand;
+use std::process::ExitCode;
+
+pub(crate) fn get_command() -> CommandHandler {
+    CommandHandler::new(
+        ClapCommand::new("validate")
+            .about("Checks the configuration file and reports all its problems")
+            .long_about("Checks the configuration file and the included files, and reports all their errors and warnings with their JSON path. Lines and columns are given for JSON and JSON5 files, and only for syntax errors in YAML and TOML files."),
+        Box::new(execute),
+    )
+}
+
+fn execute(config_file_value: Option<&str>, _args: &ArgMatches) -> Option<ExitCode> {
+    match validate(config_file_value) {
+        Ok(true) => Some(ExitCode::SUCCESS),
+        Ok(false) => Some(ExitCode::FAILURE),
+        Err(e) => {
+            error!("{}", e);
+            Some(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Returns whether the configuration file is valid.
+fn validate(config_file_value: Option<&str>) -> anyhow::Result<bool> {
+    let config_file_path = config::get_config_file(config_file_value)?;
+    let config_file = config_file_path.to_str().unwrap();
+
+    let problems = config::validate_config_file(Some(config_file))?;
+
+    for problem in &problems {
+        println!("{}", problem);
+    }
+
+    let errors = problems.iter().filter(|problem| problem.is_error()).count();
+    if errors == 0 {
+        println!("The configuration file \"{}\" is valid.", config_file);
+    }
+    if !problems.is_empty() {
+        println!(
+            "Found {} error(s) and {} warning(s).",
+            errors,
+            problems.len() - errors
+        );
+    }
+
+    Ok(errors == 0)
+}
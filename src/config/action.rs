@@ -0,0 +1,219 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+/// The command executed when a webhook is triggered.
+/// Configured either as an array ("command" followed by its arguments)
+/// or as an object with additional options.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(try_from = "RawAction")]
+pub(crate) struct Action {
+    pub(crate) command: String,
+    pub(crate) args: Vec<String>,
+    pub(crate) cwd: Option<PathBuf>,
+    pub(crate) env: HashMap<String, String>,
+    #[serde(rename = "inherit-env")]
+    pub(crate) inherit_env: InheritEnv,
+    pub(crate) shell: Option<Vec<String>>,
+    /// In seconds.
+    pub(crate) timeout: Option<u64>,
+}
+
+/// Which environment variables of the Pagoo process are passed to the action.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum InheritEnv {
+    Toggle(bool),
+    Allowlist(Vec<String>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawAction {
+    Argv(Vec<String>),
+    Object(RawActionObject),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawActionObject {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    #[serde(rename(deserialize = "inherit-env"), default)]
+    inherit_env: InheritEnv,
+    shell: Option<RawShell>,
+    timeout: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawShell {
+    Line(String),
+    Argv(Vec<String>),
+}
+
+impl Default for InheritEnv {
+    fn default() -> Self {
+        InheritEnv::Toggle(true)
+    }
+}
+
+impl InheritEnv {
+    pub(crate) fn inherits(&self, name: &str) -> bool {
+        match self {
+            InheritEnv::Toggle(inherit) => *inherit,
+            InheritEnv::Allowlist(names) => names.iter().any(|allowed| allowed == name),
+        }
+    }
+}
+
+impl Action {
+    /// The program and all its arguments, in the order they are executed.
+    /// In shell mode, the command is a script given to the shell,
+    /// and the arguments are available as "$1", "$2", etc.
+    pub(crate) fn argv(&self) -> Vec<String> {
+        match &self.shell {
+            None => std::iter::once(self.command.clone())
+                .chain(self.args.iter().cloned())
+                .collect(),
+            Some(shell) => shell
+                .iter()
+                .cloned()
+                .chain([self.command.clone(), crate::APPLICATION_NAME.to_string()])
+                .chain(self.args.iter().cloned())
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<RawAction> for Action {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawAction) -> Result<Self, Self::Error> {
+        let raw = match raw {
+            RawAction::Argv(mut argv) => {
+                if argv.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "An action must contain at least a command."
+                    ));
+                }
+                let command = argv.remove(0);
+                return Ok(Action {
+                    command,
+                    args: argv,
+                    ..Default::default()
+                });
+            }
+            RawAction::Object(raw) => raw,
+        };
+
+        if raw.command.is_empty() {
+            return Err(anyhow::anyhow!(
+                "The \"command\" of an action cannot be empty."
+            ));
+        }
+
+        let shell = match raw.shell {
+            None => None,
+            Some(RawShell::Line(line)) => Some(line.split_whitespace().map(String::from).collect()),
+            Some(RawShell::Argv(argv)) => Some(argv),
+        };
+        if shell
+            .as_ref()
+            .is_some_and(|shell: &Vec<String>| shell.is_empty())
+        {
+            return Err(anyhow::anyhow!(
+                "The \"shell\" of an action cannot be empty."
+            ));
+        }
+
+        Ok(Action {
+            command: raw.command,
+            args: raw.args,
+            cwd: raw.cwd,
+            env: raw.env,
+            inherit_env: raw.inherit_env,
+            shell,
+            timeout: raw.timeout,
+        })
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.argv().join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_array_form() {
+        let action: Action = serde_json::from_str(r#"["echo", "success!"]"#).unwrap();
+
+        assert_eq!("echo", action.command);
+        assert_eq!(vec!["success!"], action.args);
+        assert_eq!(InheritEnv::Toggle(true), action.inherit_env);
+        assert_eq!(vec!["echo", "success!"], action.argv());
+    }
+
+    #[test]
+    fn test_object_form() {
+        let action: Action = serde_json::from_str(
+            r#"{
+                "command": "git pull && make \"$1\"",
+                "args": ["release"],
+                "cwd": "/tmp",
+                "env": {"RUST_LOG": "info"},
+                "inherit-env": ["PATH", "HOME"],
+                "shell": "sh -c"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(Some(PathBuf::from("/tmp")), action.cwd);
+        assert_eq!(Some(&"info".to_string()), action.env.get("RUST_LOG"));
+        assert!(action.inherit_env.inherits("PATH"));
+        assert!(!action.inherit_env.inherits("SECRET_TOKEN"));
+        assert_eq!(
+            vec!["sh", "-c", "git pull && make \"$1\"", "pagoo", "release"],
+            action.argv()
+        );
+    }
+
+    #[test]
+    fn test_inherit_env_toggle() {
+        let action: Action =
+            serde_json::from_str(r#"{"command": "env", "inherit-env": false}"#).unwrap();
+
+        assert!(!action.inherit_env.inherits("PATH"));
+    }
+
+    #[test]
+    fn test_serialized_action_can_be_deserialized() {
+        let action: Action = serde_json::from_str(
+            r#"{"command": "make", "args": ["deploy"], "cwd": "/tmp", "inherit-env": ["PATH"], "shell": ["bash", "-c"], "timeout": 30}"#,
+        )
+        .unwrap();
+
+        let serialized = serde_json::to_string(&action).unwrap();
+
+        assert_eq!(action, serde_json::from_str::<Action>(&serialized).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_actions() {
+        assert!(serde_json::from_str::<Action>("[]").is_err());
+        assert!(serde_json::from_str::<Action>(r#"{"command": ""}"#).is_err());
+        assert!(serde_json::from_str::<Action>(r#"{"command": "ls", "shell": []}"#).is_err());
+        assert!(serde_json::from_str::<Action>(r#"{"command": "ls", "unknown": 1}"#).is_err());
+    }
+}
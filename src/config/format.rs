@@ -0,0 +1,227 @@
+use serde_json::Value;
+use std::fmt::Display;
+use std::path::Path;
+
+/// The format of a config file, detected with its extension.
+/// Files with an unknown extension are read as JSON.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) enum ConfigFormat {
+    #[default]
+    Json,
+    /// Also used for JSONC, which is JSON with comments and trailing commas.
+    Json5,
+    Yaml,
+    Toml,
+}
+
+/// A syntax error, with its line and column when the parser gives them.
+#[derive(Debug)]
+pub(crate) struct ParseError {
+    pub(crate) message: String,
+    pub(crate) position: Option<(usize, usize)>,
+}
+
+impl ConfigFormat {
+    pub(crate) const ALL: [ConfigFormat; 4] = [
+        ConfigFormat::Json,
+        ConfigFormat::Json5,
+        ConfigFormat::Yaml,
+        ConfigFormat::Toml,
+    ];
+
+    pub(crate) fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match extension.as_str() {
+            "json5" | "jsonc" => ConfigFormat::Json5,
+            "yaml" | "yml" => ConfigFormat::Yaml,
+            "toml" => ConfigFormat::Toml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.name() == name)
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ConfigFormat::Json => "json",
+            ConfigFormat::Json5 => "json5",
+            ConfigFormat::Yaml => "yaml",
+            ConfigFormat::Toml => "toml",
+        }
+    }
+
+    /// All the extensions of the format, the first one is used for new files.
+    pub(crate) fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            ConfigFormat::Json => &["json"],
+            ConfigFormat::Json5 => &["json5", "jsonc"],
+            ConfigFormat::Yaml => &["yaml", "yml"],
+            ConfigFormat::Toml => &["toml"],
+        }
+    }
+
+    /// Parses the content as a JSON document, so that all formats are interpolated,
+    /// deserialized and validated the same way.
+    pub(crate) fn parse(&self, content: &str) -> Result<Value, ParseError> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|err| {
+                ParseError::with_position(err.to_string(), Some((err.line(), err.column())))
+            }),
+            ConfigFormat::Json5 => json5::from_str(content).map_err(|err| match err {
+                json5::Error::Message { msg, location } => ParseError::with_position(
+                    msg,
+                    location.map(|location| (location.line, location.column)),
+                ),
+            }),
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|err| {
+                let position = err
+                    .location()
+                    .map(|location| (location.line(), location.column()));
+                ParseError::with_position(err.to_string(), position)
+            }),
+            ConfigFormat::Toml => toml::from_str(content).map_err(|err| {
+                let position = err.span().map(|span| line_and_column(content, span.start));
+                ParseError::with_position(err.message().to_string(), position)
+            }),
+        }
+    }
+}
+
+impl ParseError {
+    /// Parsers add the position to their messages, it is removed when it is known.
+    fn with_position(message: String, position: Option<(usize, usize)>) -> Self {
+        let message = match (position, message.rsplit_once(" at line ")) {
+            (Some(_), Some((message, _))) => message.to_string(),
+            _ => message,
+        };
+
+        ParseError { message, position }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some((line, column)) => {
+                write!(f, "{} at line {} column {}", self.message, line, column)
+            }
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Both start at 1.
+pub(crate) fn line_and_column(content: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(content.len());
+    let line_start = content[..offset].rfind('\n').map_or(0, |index| index + 1);
+
+    (
+        content[..offset].matches('\n').count() + 1,
+        content[line_start..offset].chars().count() + 1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            ConfigFormat::Json,
+            ConfigFormat::from_path(Path::new(".pagoo.json"))
+        );
+        assert_eq!(
+            ConfigFormat::Json5,
+            ConfigFormat::from_path(Path::new("pagoo.jsonc"))
+        );
+        assert_eq!(
+            ConfigFormat::Yaml,
+            ConfigFormat::from_path(Path::new("/etc/pagoo.YML"))
+        );
+        assert_eq!(
+            ConfigFormat::Toml,
+            ConfigFormat::from_path(Path::new(".pagoo.toml"))
+        );
+        assert_eq!(
+            ConfigFormat::Json,
+            ConfigFormat::from_path(Path::new("pagoo"))
+        );
+    }
+
+    #[test]
+    fn test_samples_are_equivalent() {
+        for format in ConfigFormat::ALL {
+            let sample =
+                std::fs::read_to_string(format!("samples/init_sample.{}", format.extensions()[0]))
+                    .unwrap();
+
+            let config: Config = serde_json::from_value(format.parse(&sample).unwrap()).unwrap();
+
+            assert_eq!(1, config.webhooks.len(), "Format: {}", format.name());
+            let webhook = &config.webhooks[0];
+            assert_eq!("webhook_1", webhook.name);
+            assert_eq!(2, webhook.matchers.len());
+            assert_eq!(
+                Some(serde_json::json!({"some-json-key": "some-json-value"})),
+                webhook.matchers[0].match_json_body
+            );
+            assert!(webhook.matchers[1]
+                .match_headers
+                .as_ref()
+                .unwrap()
+                .contains_key("x-some-http-header"));
+            assert_eq!(
+                vec!["echo", "success!"],
+                webhook.actions_to_execute.as_ref().unwrap().argv()
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = ConfigFormat::Yaml
+            .parse("webhooks:\n  - name: [\n")
+            .unwrap_err();
+        assert_eq!(Some(3), error.position.map(|(line, _)| line));
+
+        let error = ConfigFormat::Toml
+            .parse("[[webhooks]]\nname = \n")
+            .unwrap_err();
+        assert_eq!(Some((2, 8)), error.position);
+
+        let error = ConfigFormat::Json5
+            .parse("{\n  // Comment\n  webhooks: [,]\n}")
+            .unwrap_err();
+        assert_eq!(Some(3), error.position.map(|(line, _)| line));
+    }
+
+    #[test]
+    fn test_jsonc() {
+        let config: Config = serde_json::from_value(
+            ConfigFormat::Json5
+                .parse(
+                    r#"{
+                    // The number of workers.
+                    "workers": 2,
+                    /* No webhook yet. */
+                    "webhooks": [],
+                }"#,
+                )
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(Some(2), config.workers);
+    }
+}
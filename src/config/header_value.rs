@@ -0,0 +1,171 @@
+use glob::Pattern;
+use regex::Regex;
+use serde::Deserialize;
+use std::fmt::Display;
+
+/// The expected value of an HTTP header.
+/// Regexes and globs are compiled when the config is loaded,
+/// so they are not compiled again for each request.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RawHeaderValueMatcher")]
+pub(crate) enum HeaderValueMatcher {
+    Equals(String),
+    Regex(Regex),
+    Glob(Pattern),
+    Exists(bool),
+    Not(Box<HeaderValueMatcher>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawHeaderValueMatcher {
+    Equals(String),
+    Object(RawHeaderValueObject),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawHeaderValueObject {
+    regex: Option<String>,
+    glob: Option<String>,
+    exists: Option<bool>,
+    not: Option<Box<HeaderValueMatcher>>,
+}
+
+impl HeaderValueMatcher {
+    /// The value is "None" when the header is absent from the request.
+    pub(crate) fn matches(&self, value: Option<&str>) -> bool {
+        match self {
+            HeaderValueMatcher::Equals(expected) => value == Some(expected.as_str()),
+            HeaderValueMatcher::Regex(regex) => value.is_some_and(|v| regex.is_match(v)),
+            HeaderValueMatcher::Glob(pattern) => value.is_some_and(|v| pattern.matches(v)),
+            HeaderValueMatcher::Exists(should_exist) => value.is_some() == *should_exist,
+            HeaderValueMatcher::Not(matcher) => !matcher.matches(value),
+        }
+    }
+}
+
+impl TryFrom<RawHeaderValueMatcher> for HeaderValueMatcher {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawHeaderValueMatcher) -> Result<Self, Self::Error> {
+        let object = match raw {
+            RawHeaderValueMatcher::Equals(value) => return Ok(HeaderValueMatcher::Equals(value)),
+            RawHeaderValueMatcher::Object(object) => object,
+        };
+
+        match (object.regex, object.glob, object.exists, object.not) {
+            (Some(regex), None, None, None) => Ok(HeaderValueMatcher::Regex(
+                Regex::new(&regex)
+                    .map_err(|e| anyhow::anyhow!("Invalid regex \"{}\": {}", regex, e))?,
+            )),
+            (None, Some(glob), None, None) => Ok(HeaderValueMatcher::Glob(
+                Pattern::new(&glob)
+                    .map_err(|e| anyhow::anyhow!("Invalid glob \"{}\": {}", glob, e))?,
+            )),
+            (None, None, Some(exists), None) => Ok(HeaderValueMatcher::Exists(exists)),
+            (None, None, None, Some(not)) => Ok(HeaderValueMatcher::Not(not)),
+            _ => Err(anyhow::anyhow!(
+                "A header value must be a string, or an object with exactly one of the \"regex\", \"glob\", \"exists\" or \"not\" keys."
+            )),
+        }
+    }
+}
+
+impl Display for HeaderValueMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderValueMatcher::Equals(expected) => write!(f, "is \"{}\"", expected),
+            HeaderValueMatcher::Regex(regex) => write!(f, "matches regex \"{}\"", regex.as_str()),
+            HeaderValueMatcher::Glob(pattern) => write!(f, "matches glob \"{}\"", pattern.as_str()),
+            HeaderValueMatcher::Exists(true) => write!(f, "exists"),
+            HeaderValueMatcher::Exists(false) => write!(f, "does not exist"),
+            HeaderValueMatcher::Not(matcher) => write!(f, "not ({})", matcher),
+        }
+    }
+}
+
+impl PartialEq for HeaderValueMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (HeaderValueMatcher::Equals(a), HeaderValueMatcher::Equals(b)) => a == b,
+            (HeaderValueMatcher::Regex(a), HeaderValueMatcher::Regex(b)) => {
+                a.as_str() == b.as_str()
+            }
+            (HeaderValueMatcher::Glob(a), HeaderValueMatcher::Glob(b)) => a.as_str() == b.as_str(),
+            (HeaderValueMatcher::Exists(a), HeaderValueMatcher::Exists(b)) => a == b,
+            (HeaderValueMatcher::Not(a), HeaderValueMatcher::Not(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_json(json: &str) -> Result<HeaderValueMatcher, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn test_equals() {
+        let matcher = from_json(r#""push""#).unwrap();
+
+        assert!(matcher.matches(Some("push")));
+        assert!(!matcher.matches(Some("pushed")));
+        assert!(!matcher.matches(None));
+    }
+
+    #[test]
+    fn test_regex() {
+        let matcher = from_json(r#"{"regex": "^(push|pull_request)$"}"#).unwrap();
+
+        assert!(matcher.matches(Some("push")));
+        assert!(matcher.matches(Some("pull_request")));
+        assert!(!matcher.matches(Some("release")));
+        assert!(!matcher.matches(None));
+    }
+
+    #[test]
+    fn test_glob() {
+        let matcher = from_json(r#"{"glob": "GitHub-Hookshot/*"}"#).unwrap();
+
+        assert!(matcher.matches(Some("GitHub-Hookshot/044aadd")));
+        assert!(!matcher.matches(Some("curl/7.68.0")));
+    }
+
+    #[test]
+    fn test_exists() {
+        let matcher = from_json(r#"{"exists": true}"#).unwrap();
+        assert!(matcher.matches(Some("")));
+        assert!(!matcher.matches(None));
+
+        let matcher = from_json(r#"{"exists": false}"#).unwrap();
+        assert!(!matcher.matches(Some("")));
+        assert!(matcher.matches(None));
+    }
+
+    #[test]
+    fn test_not() {
+        let matcher = from_json(r#"{"not": {"regex": "^refs/tags/"}}"#).unwrap();
+
+        assert!(matcher.matches(Some("refs/heads/main")));
+        assert!(!matcher.matches(Some("refs/tags/v1.0.0")));
+        assert_eq!("not (matches regex \"^refs/tags/\")", matcher.to_string());
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let error = from_json(r#"{"regex": "(unclosed"}"#).unwrap_err();
+
+        assert!(error.to_string().starts_with("Invalid regex \"(unclosed\""));
+    }
+
+    #[test]
+    fn test_multiple_keys() {
+        let error = from_json(r#"{"regex": "push", "glob": "push*"}"#).unwrap_err();
+
+        assert!(error.to_string().starts_with("A header value must be"));
+    }
+}
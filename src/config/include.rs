@@ -0,0 +1,156 @@
+use crate::config::ConfigFormat;
+use crate::config::Webhook;
+use serde::Deserialize;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Included files can only contain webhooks, and cannot include other files.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct IncludedFile {
+    pub(crate) webhooks: Vec<Webhook>,
+}
+
+/// The files of the "include" option, and the errors of its patterns with their index.
+#[derive(Debug, Default)]
+pub(crate) struct Includes {
+    pub(crate) files: Vec<PathBuf>,
+    pub(crate) errors: Vec<(usize, String)>,
+}
+
+impl Includes {
+    pub(crate) fn into_result(self) -> anyhow::Result<Vec<PathBuf>> {
+        match self.errors.into_iter().next() {
+            None => Ok(self.files),
+            Some((index, message)) => Err(anyhow::anyhow!(
+                "Invalid \"include\" pattern at index {}: {}",
+                index,
+                message
+            )),
+        }
+    }
+}
+
+/// Resolves the "include" patterns, relative to the directory of the config file.
+/// A pattern can be a directory, which includes all its config files like a "conf.d" directory,
+/// a file, or a glob. Files are sorted for each pattern, and only included once.
+pub(crate) fn included_files(config_file: &Path, patterns: &[String]) -> Includes {
+    let base_dir = config_file.parent().unwrap_or(Path::new("."));
+    let config_file = config_file.canonicalize().ok();
+    let mut includes = Includes::default();
+    let mut known_files = Vec::new();
+
+    for (index, pattern) in patterns.iter().enumerate() {
+        let files = match resolve_pattern(base_dir, pattern) {
+            Ok(files) => files,
+            Err(message) => {
+                includes.errors.push((index, message));
+                continue;
+            }
+        };
+
+        for file in files {
+            let canonical_file = file.canonicalize().ok();
+            if canonical_file == config_file || known_files.contains(&canonical_file) {
+                continue;
+            }
+            known_files.push(canonical_file);
+            includes.files.push(file);
+        }
+    }
+
+    includes
+}
+
+fn resolve_pattern(base_dir: &Path, pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let path = base_dir.join(pattern);
+
+    if path.is_dir() {
+        let entries = std::fs::read_dir(&path)
+            .map_err(|err| format!("Could not read directory \"{}\": {}", pattern, err))?;
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| file.is_file() && has_config_extension(file))
+            .collect();
+        files.sort();
+        return Ok(files);
+    }
+
+    let is_glob = pattern.contains(['*', '?', '[']);
+    if !is_glob {
+        return match path.is_file() {
+            true => Ok(vec![path]),
+            false => Err(format!("Included file \"{}\" was not found.", pattern)),
+        };
+    }
+
+    // Unlike files, a glob matching nothing is valid, for example an empty "conf.d" directory.
+    let paths = glob::glob(path.to_str().unwrap_or(pattern))
+        .map_err(|err| format!("Invalid glob \"{}\": {}", pattern, err))?;
+    let mut files: Vec<PathBuf> = paths
+        .filter_map(Result::ok)
+        .filter(|file| file.is_file())
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+/// Other files of "conf.d" directories, like backups or READMEs, are ignored.
+fn has_config_extension(file: &Path) -> bool {
+    let extension = file
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    ConfigFormat::ALL
+        .iter()
+        .any(|format| format.extensions().contains(&extension.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_included_files() {
+        let dir = std::env::temp_dir().join(format!("pagoo-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("conf.d")).unwrap();
+        let config_file = dir.join("pagoo.json");
+        for file in [
+            "pagoo.json",
+            "extra.yaml",
+            "conf.d/b.toml",
+            "conf.d/a.json",
+            "conf.d/README.md",
+        ] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let includes = included_files(
+            &config_file,
+            &[
+                "conf.d".to_string(),
+                "*.*".to_string(),
+                "conf.d/a.json".to_string(),
+                "nothing/*.json".to_string(),
+                "missing.json".to_string(),
+            ],
+        );
+
+        assert_eq!(
+            vec![
+                dir.join("conf.d/a.json"),
+                dir.join("conf.d/b.toml"),
+                dir.join("extra.yaml"),
+            ],
+            includes.files
+        );
+        assert_eq!(1, includes.errors.len());
+        assert_eq!(4, includes.errors[0].0);
+        assert!(includes.errors[0].1.contains("was not found"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
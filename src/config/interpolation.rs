@@ -0,0 +1,441 @@
+use crate::config::validation::format_path;
+use crate::config::validation::PathSegment;
+use serde_json::Value;
+use std::path::Path;
+
+/// Resolved values shorter than this are not redacted from the logs:
+/// they are unlikely to be secrets, and hiding them would make the logs unreadable.
+const MIN_SECRET_LENGTH: usize = 4;
+
+/// A resolved value to redact from the logs, with the placeholder it was resolved from.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Secret {
+    pub(crate) value: String,
+    /// Relative secret files are made absolute, so that it can be resolved again from anywhere.
+    pub(crate) placeholder: String,
+}
+
+/// The result of the interpolation of a config.
+#[derive(Debug, Default)]
+pub(crate) struct Interpolation {
+    /// The values of the secret files, and of the environment variables used in secret options,
+    /// to redact from the logs.
+    pub(crate) secrets: Vec<Secret>,
+    pub(crate) errors: Vec<(Vec<PathSegment>, String)>,
+}
+
+impl Interpolation {
+    pub(crate) fn into_result(self) -> anyhow::Result<Vec<Secret>> {
+        if self.errors.is_empty() {
+            return Ok(self.secrets);
+        }
+
+        Err(anyhow::anyhow!(
+            "Could not resolve the placeholders of the config: {}",
+            self.errors
+                .iter()
+                .map(|(path, message)| format!("{}: {}", format_path(path), message))
+                .collect::<Vec<String>>()
+                .join(" ")
+        ))
+    }
+}
+
+/// Replaces the "${NAME}", "${NAME:-default}" and "${file:/path}" placeholders
+/// of all the string values, but not of the keys. Relative secret files are
+/// searched from "base_dir", the directory of the config file.
+///
+/// Environment variables are also used for values that are not secrets, like paths
+/// or branch names, so they are only redacted when they are used in a secret option.
+pub(crate) fn interpolate(value: &mut Value, base_dir: &Path) -> Interpolation {
+    let mut interpolation = Interpolation::default();
+
+    interpolate_value(value, base_dir, &mut Vec::new(), &mut interpolation);
+
+    interpolation
+}
+
+fn interpolate_value(
+    value: &mut Value,
+    base_dir: &Path,
+    path: &mut Vec<PathSegment>,
+    interpolation: &mut Interpolation,
+) {
+    match value {
+        Value::String(string) => {
+            let secrets = match is_secret_option(path) {
+                true => Secrets::All,
+                false => Secrets::Files,
+            };
+            match interpolate_string(string, base_dir, secrets, &mut interpolation.secrets) {
+                Ok(interpolated) => *string = interpolated,
+                Err(message) => interpolation.errors.push((path.clone(), message)),
+            }
+        }
+        Value::Array(values) => {
+            for (index, value) in values.iter_mut().enumerate() {
+                path.push(PathSegment::Index(index));
+                interpolate_value(value, base_dir, path, interpolation);
+                path.pop();
+            }
+        }
+        Value::Object(values) => {
+            for (key, value) in values.iter_mut() {
+                path.push(PathSegment::Key(key.clone()));
+                interpolate_value(value, base_dir, path, interpolation);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Which resolved values of a string are secrets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Secrets {
+    Files,
+    All,
+}
+
+/// The "secret" and "api-token" options, the secret of the "verification" option,
+/// and the environment variables given to the actions.
+fn is_secret_option(path: &[PathSegment]) -> bool {
+    let keys: Vec<&str> = path
+        .iter()
+        .filter_map(|segment| match segment {
+            PathSegment::Key(key) => Some(key.as_str()),
+            PathSegment::Index(_) => None,
+        })
+        .collect();
+
+    matches!(
+        keys.as_slice(),
+        ["api-token"]
+            | ["webhooks", "secret"]
+            | ["webhooks", "verification", "secret"]
+            | ["webhooks", "actions-to-execute", "env", _]
+            | ["webhooks", "actions", "run", "env", _]
+    )
+}
+
+/// "$${" is an escaped "${", for example for shell scripts using their own variables.
+fn interpolate_string(
+    value: &str,
+    base_dir: &Path,
+    secret_values: Secrets,
+    secrets: &mut Vec<Secret>,
+) -> Result<String, String> {
+    let mut interpolated = String::new();
+    let mut rest = value;
+
+    while let Some(index) = rest.find('$') {
+        interpolated.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        if let Some(after_escape) = rest.strip_prefix("$${") {
+            interpolated.push_str("${");
+            rest = after_escape;
+            continue;
+        }
+        let placeholder = match rest.strip_prefix("${") {
+            Some(placeholder) => placeholder,
+            None => {
+                interpolated.push('$');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+
+        let end = placeholder
+            .find('}')
+            .ok_or_else(|| format!("The placeholder of \"{}\" is not closed.", value))?;
+        interpolated.push_str(&resolve(
+            &placeholder[..end],
+            base_dir,
+            secret_values,
+            secrets,
+        )?);
+        rest = &placeholder[end + 1..];
+    }
+    interpolated.push_str(rest);
+
+    Ok(interpolated)
+}
+
+fn resolve(
+    placeholder: &str,
+    base_dir: &Path,
+    secret_values: Secrets,
+    secrets: &mut Vec<Secret>,
+) -> Result<String, String> {
+    if let Some(file) = placeholder.strip_prefix("file:") {
+        let file = base_dir.join(file);
+        let secret = std::fs::read_to_string(&file).map_err(|err| {
+            format!(
+                "Could not read secret file \"{}\": {}",
+                placeholder.trim_start_matches("file:"),
+                err
+            )
+        })?;
+        // Secret files usually end with a new line, which is not part of the secret.
+        let secret = secret.trim_end_matches(['\n', '\r']).to_string();
+        add_secret(secrets, &secret, format!("${{file:{}}}", file.display()));
+        return Ok(secret);
+    }
+
+    let (name, default) = match placeholder.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (placeholder, None),
+    };
+
+    let is_valid_name = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_valid_name {
+        return Err(format!(
+            "Invalid placeholder \"${{{}}}\", expected an environment variable name or \"file:\" followed by a path.",
+            placeholder
+        ));
+    }
+
+    // Like in shells, the default value is also used for empty variables.
+    match (std::env::var(name), default) {
+        (Ok(value), Some(default)) if value.is_empty() => Ok(default.to_string()),
+        (Ok(value), _) => {
+            if secret_values == Secrets::All {
+                add_secret(secrets, &value, format!("${{{}}}", placeholder));
+            }
+            Ok(value)
+        }
+        (Err(std::env::VarError::NotPresent), Some(default)) => Ok(default.to_string()),
+        (Err(std::env::VarError::NotPresent), None) => Err(format!(
+            "Environment variable \"{}\" is not set, you can set a default value with \"${{{}:-default}}\".",
+            name, name
+        )),
+        (Err(std::env::VarError::NotUnicode(_)), _) => Err(format!(
+            "Environment variable \"{}\" is not valid unicode.",
+            name
+        )),
+    }
+}
+
+fn add_secret(secrets: &mut Vec<Secret>, secret: &str, placeholder: String) {
+    if secret.len() >= MIN_SECRET_LENGTH && !secrets.iter().any(|known| known.value == secret) {
+        secrets.push(Secret {
+            value: secret.to_string(),
+            placeholder,
+        });
+    }
+}
+
+/// Resolves a value stored with "to_template()".
+/// Secret files are stored with absolute paths, so no base directory is needed.
+pub(crate) fn from_template(value: &mut Value) -> anyhow::Result<()> {
+    interpolate(value, Path::new("/")).into_result()?;
+
+    Ok(())
+}
+
+/// The reverse of "interpolate()": replaces the secrets of the string values with their
+/// placeholders, and escapes the other "${", so that the value can be stored without
+/// its secrets, and interpolated again to get it back.
+pub(crate) fn to_template(value: &mut Value, secrets: &[Secret]) {
+    match value {
+        Value::String(string) => *string = template_string(string, secrets),
+        Value::Array(values) => {
+            for value in values.iter_mut() {
+                to_template(value, secrets);
+            }
+        }
+        Value::Object(values) => {
+            for (_, value) in values.iter_mut() {
+                to_template(value, secrets);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn template_string(value: &str, secrets: &[Secret]) -> String {
+    let mut template = String::new();
+    let mut rest = value;
+
+    while let Some(first_char) = rest.chars().next() {
+        // Longer secrets first, in case they contain shorter ones.
+        let secret = secrets
+            .iter()
+            .filter(|secret| rest.starts_with(secret.value.as_str()))
+            .max_by_key(|secret| secret.value.len());
+
+        if let Some(secret) = secret {
+            template.push_str(&secret.placeholder);
+            rest = &rest[secret.value.len()..];
+        } else if let Some(after_placeholder) = rest.strip_prefix("${") {
+            template.push_str("$${");
+            rest = after_placeholder;
+        } else {
+            template.push(first_char);
+            rest = &rest[first_char.len_utf8()..];
+        }
+    }
+
+    template
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolate_string() {
+        std::env::set_var("PAGOO_TEST_INTERPOLATION", "s3cr3t-value");
+        std::env::set_var("PAGOO_TEST_INTERPOLATION_EMPTY", "");
+        let mut secrets = Vec::new();
+        let base_dir = Path::new("/");
+
+        assert_eq!(
+            Ok("token: s3cr3t-value".to_string()),
+            interpolate_string(
+                "token: ${PAGOO_TEST_INTERPOLATION}",
+                base_dir,
+                Secrets::All,
+                &mut secrets
+            )
+        );
+        assert_eq!(
+            Ok("fallback/fallback".to_string()),
+            interpolate_string(
+                "${PAGOO_TEST_INTERPOLATION_UNSET:-fallback}/${PAGOO_TEST_INTERPOLATION_EMPTY:-fallback}",
+                base_dir,
+                Secrets::All,
+                &mut secrets
+            )
+        );
+        assert_eq!(
+            Ok("echo ${HOME} costs $5".to_string()),
+            interpolate_string(
+                "echo $${HOME} costs $5",
+                base_dir,
+                Secrets::All,
+                &mut secrets
+            )
+        );
+        assert_eq!(
+            vec![Secret {
+                value: "s3cr3t-value".to_string(),
+                placeholder: "${PAGOO_TEST_INTERPOLATION}".to_string(),
+            }],
+            secrets
+        );
+
+        assert!(interpolate_string(
+            "${PAGOO_TEST_INTERPOLATION_UNSET}",
+            base_dir,
+            Secrets::All,
+            &mut secrets
+        )
+        .unwrap_err()
+        .contains("is not set"));
+        assert!(interpolate_string(
+            "${PAGOO_TEST_INTERPOLATION",
+            base_dir,
+            Secrets::All,
+            &mut secrets
+        )
+        .unwrap_err()
+        .contains("is not closed"));
+        assert!(
+            interpolate_string("${not a name}", base_dir, Secrets::All, &mut secrets)
+                .unwrap_err()
+                .contains("Invalid placeholder")
+        );
+    }
+
+    #[test]
+    fn test_only_secrets_are_redacted() {
+        std::env::set_var("PAGOO_TEST_BRANCH", "refs/heads/main");
+        std::env::set_var("PAGOO_TEST_SECRET", "webhook-secret");
+        std::env::set_var("PAGOO_TEST_TOKEN", "deploy-token");
+        let mut value = serde_json::json!({
+            "database_file": "${PAGOO_TEST_BRANCH}.db3",
+            "webhooks": [{
+                "name": "${PAGOO_TEST_BRANCH}",
+                "verification": {"provider": "gitlab", "secret": "${PAGOO_TEST_SECRET}"},
+                "actions": [{
+                    "name": "deploy",
+                    "run": {"command": "deploy ${PAGOO_TEST_BRANCH}", "env": {"TOKEN": "Bearer ${PAGOO_TEST_TOKEN}"}}
+                }]
+            }]
+        });
+
+        let interpolation = interpolate(&mut value, Path::new("/"));
+
+        assert!(interpolation.errors.is_empty());
+        assert_eq!(
+            "deploy refs/heads/main",
+            value["webhooks"][0]["actions"][0]["run"]["command"]
+        );
+        assert_eq!(
+            vec!["deploy-token", "webhook-secret"],
+            interpolation
+                .secrets
+                .iter()
+                .map(|secret| secret.value.as_str())
+                .collect::<Vec<&str>>()
+        );
+    }
+
+    #[test]
+    fn test_template() {
+        std::env::set_var("PAGOO_TEST_TEMPLATE_TOKEN", "deploy-token");
+        let template = serde_json::json!({
+            "webhooks": [{
+                "actions": [{
+                    "run": {"command": "echo $${HOME}", "env": {"TOKEN": "Bearer ${PAGOO_TEST_TEMPLATE_TOKEN}"}}
+                }]
+            }]
+        });
+        let mut value = template.clone();
+        let secrets = interpolate(&mut value, Path::new("/")).secrets;
+        let interpolated = value.clone();
+
+        to_template(&mut value, &secrets);
+
+        assert_eq!(template, value);
+        from_template(&mut value).unwrap();
+        assert_eq!(interpolated, value);
+    }
+
+    #[test]
+    fn test_secret_file() {
+        let secret_file = std::env::temp_dir().join(format!("pagoo-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&secret_file, "file-secret\n").unwrap();
+        let mut value = serde_json::json!({
+            "webhooks": [{"secret": format!("${{file:{}}}", secret_file.file_name().unwrap().to_str().unwrap())}]
+        });
+
+        let interpolation = interpolate(&mut value, &std::env::temp_dir());
+
+        assert_eq!("file-secret", value["webhooks"][0]["secret"]);
+        assert_eq!(
+            vec![Secret {
+                value: "file-secret".to_string(),
+                placeholder: format!("${{file:{}}}", secret_file.display()),
+            }],
+            interpolation.secrets
+        );
+        assert!(interpolation.errors.is_empty());
+
+        std::fs::remove_file(&secret_file).unwrap();
+        let mut value =
+            serde_json::json!({"api-token": format!("${{file:{}}}", secret_file.display())});
+
+        let errors = interpolate(&mut value, Path::new("/")).errors;
+
+        assert_eq!(1, errors.len());
+        assert_eq!("$.api-token", format_path(&errors[0].0));
+        assert!(errors[0].1.starts_with("Could not read secret file"));
+    }
+}
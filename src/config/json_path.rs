@@ -0,0 +1,338 @@
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt::Display;
+
+/// A condition of the "match-json-path" matcher.
+/// Paths and regexes are parsed when the config is loaded.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RawJsonPathCondition")]
+pub(crate) struct JsonPathCondition {
+    pub(crate) path: JsonPath,
+    pub(crate) operator: JsonPathOperator,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum JsonPathOperator {
+    Equals(Value),
+    Regex(Regex),
+    In(Vec<Value>),
+    Exists(bool),
+    GreaterThan(f64),
+    LowerThan(f64),
+    Contains(Value),
+}
+
+/// Either a JSON Pointer (RFC 6901), like "/commits/0/id",
+/// or a JSONPath expression, like "$.commits[*].id".
+/// Only the child, index and wildcard JSONPath selectors are supported.
+#[derive(Debug, Clone)]
+pub(crate) struct JsonPath {
+    expression: String,
+    kind: JsonPathKind,
+}
+
+#[derive(Debug, Clone)]
+enum JsonPathKind {
+    Pointer,
+    Path(Vec<JsonPathSegment>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum JsonPathSegment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawJsonPathCondition {
+    path: String,
+    #[serde(default, deserialize_with = "present_value")]
+    equals: Option<Value>,
+    regex: Option<String>,
+    #[serde(rename = "in")]
+    is_in: Option<Vec<Value>>,
+    exists: Option<bool>,
+    gt: Option<f64>,
+    lt: Option<f64>,
+    #[serde(default, deserialize_with = "present_value")]
+    contains: Option<Value>,
+}
+
+/// Reads an operator that is present in the condition, even when its value is null,
+/// so that "equals": null compares against JSON null instead of being ignored.
+fn present_value<'de, D>(deserializer: D) -> Result<Option<Value>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Value::deserialize(deserializer).map(Some)
+}
+
+impl TryFrom<RawJsonPathCondition> for JsonPathCondition {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawJsonPathCondition) -> Result<Self, Self::Error> {
+        let path = JsonPath::parse(&raw.path)?;
+
+        let mut operators = Vec::new();
+
+        if let Some(value) = raw.equals {
+            operators.push(JsonPathOperator::Equals(value));
+        }
+        if let Some(regex) = raw.regex {
+            operators
+                .push(JsonPathOperator::Regex(Regex::new(&regex).map_err(
+                    |e| anyhow::anyhow!("Invalid regex \"{}\": {}", regex, e),
+                )?));
+        }
+        if let Some(values) = raw.is_in {
+            operators.push(JsonPathOperator::In(values));
+        }
+        if let Some(exists) = raw.exists {
+            operators.push(JsonPathOperator::Exists(exists));
+        }
+        if let Some(number) = raw.gt {
+            operators.push(JsonPathOperator::GreaterThan(number));
+        }
+        if let Some(number) = raw.lt {
+            operators.push(JsonPathOperator::LowerThan(number));
+        }
+        if let Some(value) = raw.contains {
+            operators.push(JsonPathOperator::Contains(value));
+        }
+
+        if operators.len() != 1 {
+            return Err(anyhow::anyhow!(
+                "JSON path \"{}\" must have exactly one of the \"equals\", \"regex\", \"in\", \"exists\", \"gt\", \"lt\" or \"contains\" operators.",
+                raw.path
+            ));
+        }
+
+        Ok(JsonPathCondition {
+            path,
+            operator: operators.remove(0),
+        })
+    }
+}
+
+impl JsonPath {
+    pub(crate) fn parse(expression: &str) -> Result<Self, anyhow::Error> {
+        let kind = if expression.is_empty() || expression.starts_with('/') {
+            JsonPathKind::Pointer
+        } else if let Some(path) = expression.strip_prefix('$') {
+            JsonPathKind::Path(
+                parse_segments(path)
+                    .map_err(|e| anyhow::anyhow!("Invalid JSON path \"{}\": {}", expression, e))?,
+            )
+        } else {
+            return Err(anyhow::anyhow!(
+                "Invalid JSON path \"{}\": it must start with \"$\" (JSONPath) or \"/\" (JSON Pointer).",
+                expression
+            ));
+        };
+
+        Ok(Self {
+            expression: expression.to_string(),
+            kind,
+        })
+    }
+
+    /// Returns all the values of the document targeted by this path.
+    pub(crate) fn resolve<'a>(&self, document: &'a Value) -> Vec<&'a Value> {
+        match &self.kind {
+            JsonPathKind::Pointer => document.pointer(&self.expression).into_iter().collect(),
+            JsonPathKind::Path(segments) => {
+                let mut values = vec![document];
+
+                for segment in segments {
+                    values = values
+                        .into_iter()
+                        .flat_map(|value| resolve_segment(segment, value))
+                        .collect();
+                }
+
+                values
+            }
+        }
+    }
+}
+
+impl Display for JsonPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl Display for JsonPathOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonPathOperator::Equals(value) => write!(f, "equals {}", value),
+            JsonPathOperator::Regex(regex) => write!(f, "matches regex \"{}\"", regex.as_str()),
+            JsonPathOperator::In(values) => write!(f, "is in {}", Value::from(values.clone())),
+            JsonPathOperator::Exists(true) => write!(f, "exists"),
+            JsonPathOperator::Exists(false) => write!(f, "does not exist"),
+            JsonPathOperator::GreaterThan(number) => write!(f, "is greater than {}", number),
+            JsonPathOperator::LowerThan(number) => write!(f, "is lower than {}", number),
+            JsonPathOperator::Contains(value) => write!(f, "contains {}", value),
+        }
+    }
+}
+
+fn resolve_segment<'a>(segment: &JsonPathSegment, value: &'a Value) -> Vec<&'a Value> {
+    match (segment, value) {
+        (JsonPathSegment::Key(key), Value::Object(map)) => map.get(key).into_iter().collect(),
+        (JsonPathSegment::Index(index), Value::Array(array)) => {
+            array.get(*index).into_iter().collect()
+        }
+        (JsonPathSegment::Wildcard, Value::Array(array)) => array.iter().collect(),
+        (JsonPathSegment::Wildcard, Value::Object(map)) => map.values().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_segments(path: &str) -> Result<Vec<JsonPathSegment>, String> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            let key = &after_dot[..end];
+            if key.is_empty() {
+                return Err("empty key after \".\"".to_string());
+            }
+            segments.push(if key == "*" {
+                JsonPathSegment::Wildcard
+            } else {
+                JsonPathSegment::Key(key.to_string())
+            });
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = after_bracket
+                .find(']')
+                .ok_or_else(|| "unclosed \"[\"".to_string())?;
+            let selector = after_bracket[..end].trim();
+            segments.push(parse_bracket_selector(selector)?);
+            rest = &after_bracket[end + 1..];
+        } else {
+            return Err(format!("unexpected \"{}\"", rest));
+        }
+    }
+
+    Ok(segments)
+}
+
+fn parse_bracket_selector(selector: &str) -> Result<JsonPathSegment, String> {
+    if selector == "*" {
+        return Ok(JsonPathSegment::Wildcard);
+    }
+
+    for quote in ['\'', '"'] {
+        if let Some(key) = selector
+            .strip_prefix(quote)
+            .and_then(|s| s.strip_suffix(quote))
+        {
+            return Ok(JsonPathSegment::Key(key.to_string()));
+        }
+    }
+
+    selector
+        .parse::<usize>()
+        .map(JsonPathSegment::Index)
+        .map_err(|_| format!("invalid selector \"[{}]\"", selector))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_json_path() {
+        let path = JsonPath::parse("$.commits[*]['modified'][0].name").unwrap();
+
+        match path.kind {
+            JsonPathKind::Path(segments) => assert_eq!(
+                segments,
+                vec![
+                    JsonPathSegment::Key("commits".to_string()),
+                    JsonPathSegment::Wildcard,
+                    JsonPathSegment::Key("modified".to_string()),
+                    JsonPathSegment::Index(0),
+                    JsonPathSegment::Key("name".to_string()),
+                ]
+            ),
+            JsonPathKind::Pointer => panic!("Expected a JSONPath expression."),
+        }
+    }
+
+    #[test]
+    fn test_invalid_json_path() {
+        assert!(JsonPath::parse("commits").is_err());
+        assert!(JsonPath::parse("$.commits[").is_err());
+        assert!(JsonPath::parse("$.commits[first]").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let document = json!({
+            "ref": "refs/heads/main",
+            "commits": [
+                {"modified": ["src/main.rs"]},
+                {"modified": ["deploy/script.sh", "README.md"]}
+            ]
+        });
+
+        let path = JsonPath::parse("$.commits[*].modified[*]").unwrap();
+        assert_eq!(
+            path.resolve(&document),
+            vec!["src/main.rs", "deploy/script.sh", "README.md"]
+        );
+
+        let path = JsonPath::parse("/commits/1/modified/0").unwrap();
+        assert_eq!(path.resolve(&document), vec!["deploy/script.sh"]);
+
+        let path = JsonPath::parse("$.unknown.key").unwrap();
+        assert!(path.resolve(&document).is_empty());
+    }
+
+    #[test]
+    fn test_condition_needs_exactly_one_operator() {
+        let result = serde_json::from_str::<JsonPathCondition>(r#"{"path": "$.ref"}"#);
+        assert!(result.is_err());
+
+        let result = serde_json::from_str::<JsonPathCondition>(
+            r#"{"path": "$.ref", "equals": "a", "regex": "b"}"#,
+        );
+        assert!(result.is_err());
+
+        let result =
+            serde_json::from_str::<JsonPathCondition>(r#"{"path": "$.ref", "regex": "^refs/"}"#);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_null_operator_value() {
+        let condition =
+            serde_json::from_str::<JsonPathCondition>(r#"{"path": "$.ref", "equals": null}"#)
+                .unwrap();
+        assert!(matches!(
+            condition.operator,
+            JsonPathOperator::Equals(Value::Null)
+        ));
+
+        let condition: JsonPathCondition =
+            serde_yaml::from_str("path: $.ref\ncontains: null").unwrap();
+        assert!(matches!(
+            condition.operator,
+            JsonPathOperator::Contains(Value::Null)
+        ));
+
+        let result = serde_json::from_str::<JsonPathCondition>(
+            r#"{"path": "$.ref", "equals": null, "regex": "b"}"#,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use crate::config::Matcher;
+use crate::config::MatchersStrategy;
+use serde::Deserialize;
+
+/// A tree of matchers, combined with "all", "any" and "not" groups.
+/// Each leaf is a matcher whose conditions must all match.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RawMatcherExpression")]
+pub(crate) enum MatcherExpression {
+    All(Vec<MatcherExpression>),
+    Any(Vec<MatcherExpression>),
+    Not(Box<MatcherExpression>),
+    Matcher(Matcher),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawMatcherExpression {
+    Group(RawMatcherGroup),
+    Matcher(Matcher),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawMatcherGroup {
+    all: Option<Vec<MatcherExpression>>,
+    any: Option<Vec<MatcherExpression>>,
+    not: Option<Box<MatcherExpression>>,
+}
+
+impl MatcherExpression {
+    /// Translates the "matchers" and "matchers-strategy" options into an expression.
+    /// Conditions of a single legacy matcher are alternatives: one of them is enough to match.
+    pub(crate) fn from_legacy_matchers(matchers: &[Matcher], strategy: MatchersStrategy) -> Self {
+        let expressions = matchers
+            .iter()
+            .map(|matcher| MatcherExpression::Any(split_matcher_conditions(matcher)))
+            .collect();
+
+        match strategy {
+            MatchersStrategy::All => MatcherExpression::All(expressions),
+            MatchersStrategy::One => MatcherExpression::Any(expressions),
+        }
+    }
+}
+
+impl TryFrom<RawMatcherExpression> for MatcherExpression {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawMatcherExpression) -> Result<Self, Self::Error> {
+        let group = match raw {
+            RawMatcherExpression::Matcher(matcher) => {
+                if !matcher.has_conditions() {
+                    return Err(anyhow::anyhow!(
+                        "A matcher must contain at least one of the \"match-headers\", \"match-json-body\" or \"match-json-path\" keys."
+                    ));
+                }
+                return Ok(MatcherExpression::Matcher(matcher));
+            }
+            RawMatcherExpression::Group(group) => group,
+        };
+
+        match (group.all, group.any, group.not) {
+            (Some(all), None, None) => Ok(MatcherExpression::All(all)),
+            (None, Some(any), None) => Ok(MatcherExpression::Any(any)),
+            (None, None, Some(not)) => Ok(MatcherExpression::Not(not)),
+            _ => Err(anyhow::anyhow!(
+                "A matcher group must contain exactly one of the \"all\", \"any\" or \"not\" keys."
+            )),
+        }
+    }
+}
+
+fn split_matcher_conditions(matcher: &Matcher) -> Vec<MatcherExpression> {
+    let mut conditions = Vec::new();
+
+    if matcher.match_headers.is_some() {
+        conditions.push(MatcherExpression::Matcher(Matcher {
+            match_headers: matcher.match_headers.clone(),
+            ..Default::default()
+        }));
+    }
+
+    if matcher.match_json_body.is_some() {
+        conditions.push(MatcherExpression::Matcher(Matcher {
+            match_json_body: matcher.match_json_body.clone(),
+            json_compare_mode: matcher.json_compare_mode,
+            json_array_mode: matcher.json_array_mode,
+            ..Default::default()
+        }));
+    }
+
+    if matcher.match_json_path.is_some() {
+        conditions.push(MatcherExpression::Matcher(Matcher {
+            match_json_path: matcher.match_json_path.clone(),
+            ..Default::default()
+        }));
+    }
+
+    conditions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_expression() {
+        let expression: MatcherExpression = serde_json::from_str(
+            r#"{
+                "any": [
+                    {"all": [
+                        {"match-headers": {"x-github-event": "push"}},
+                        {"match-json-body": {"ref": "refs/heads/main"}}
+                    ]},
+                    {"match-headers": {"x-github-event": "release"}, "match-json-body": {"action": "published"}},
+                    {"not": {"match-json-body": {"release": {"prerelease": true}}}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let expressions = match expression {
+            MatcherExpression::Any(expressions) => expressions,
+            _ => panic!("Expected an \"any\" group."),
+        };
+        assert_eq!(3, expressions.len());
+        assert!(matches!(&expressions[0], MatcherExpression::All(all) if all.len() == 2));
+        assert!(matches!(&expressions[1], MatcherExpression::Matcher(_)));
+        assert!(matches!(&expressions[2], MatcherExpression::Not(_)));
+    }
+
+    #[test]
+    fn test_invalid_group() {
+        let result = serde_json::from_str::<MatcherExpression>(r#"{"all": [], "any": []}"#);
+        assert!(result.is_err());
+
+        let result = serde_json::from_str::<MatcherExpression>(r#"{}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_from_legacy_matchers() {
+        let matchers: Vec<Matcher> = serde_json::from_str(
+            r#"[
+                {"match-headers": {"x-github-event": "push"}, "match-json-body": {"ref": "refs/heads/main"}},
+                {"match-json-body": {"action": "published"}}
+            ]"#,
+        )
+        .unwrap();
+
+        let expression = MatcherExpression::from_legacy_matchers(&matchers, MatchersStrategy::One);
+
+        let expressions = match expression {
+            MatcherExpression::Any(expressions) => expressions,
+            _ => panic!("Expected an \"any\" group."),
+        };
+        assert!(matches!(&expressions[0], MatcherExpression::Any(any) if any.len() == 2));
+        assert!(matches!(&expressions[1], MatcherExpression::Any(any) if any.len() == 1));
+    }
+}
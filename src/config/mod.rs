@@ -1,7 +1,40 @@
+mod action;
+mod format;
+mod header_value;
+mod include;
+mod interpolation;
+mod json_path;
+mod matcher_expression;
+mod retry;
+mod shared;
+mod step;
+mod validation;
+
+pub(crate) use action::Action;
+pub(crate) use action::InheritEnv;
+pub(crate) use format::ConfigFormat;
+pub(crate) use header_value::HeaderValueMatcher;
+pub(crate) use include::included_files;
+pub(crate) use interpolation::from_template;
+pub(crate) use interpolation::to_template;
+pub(crate) use interpolation::Secret;
+pub(crate) use json_path::JsonPathCondition;
+pub(crate) use json_path::JsonPathOperator;
+pub(crate) use matcher_expression::MatcherExpression;
+pub(crate) use retry::RetryPolicy;
+pub(crate) use shared::SharedConfig;
+pub(crate) use step::RunIf;
+pub(crate) use step::Step;
+pub(crate) use validation::validate_config_file;
+pub(crate) use validation::Problem;
+
 use crate::APPLICATION_NAME;
 use serde::Deserialize;
+use serde::Serialize;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::path::Path;
 use std::path::PathBuf;
 
 #[derive(Debug, Default, Deserialize)]
@@ -9,6 +42,19 @@ pub(crate) struct Config {
     #[serde(skip_deserializing)]
     config_file: String,
     pub(crate) database_file: Option<String>,
+    /// Number of actions that can be executed at the same time.
+    pub(crate) workers: Option<usize>,
+    /// In seconds, used for actions that do not have a "timeout".
+    #[serde(rename(deserialize = "default-timeout"))]
+    pub(crate) default_timeout: Option<u64>,
+    /// Bearer token required by the "/api" endpoints, which are disabled without it.
+    #[serde(rename(deserialize = "api-token"))]
+    pub(crate) api_token: Option<String>,
+    /// Files, directories or globs of files containing more webhooks,
+    /// relative to the directory of the config file.
+    #[serde(default)]
+    pub(crate) include: Vec<String>,
+    #[serde(default)]
     pub(crate) webhooks: Vec<Webhook>,
 }
 
@@ -17,25 +63,111 @@ pub(crate) struct Webhook {
     pub(crate) name: String,
     #[serde(rename(deserialize = "matchers-strategy"))]
     pub(crate) matchers_strategy: Option<MatchersStrategy>,
+    #[serde(default)]
     pub(crate) matchers: Vec<Matcher>,
+    #[serde(rename(deserialize = "match"))]
+    pub(crate) match_expression: Option<MatcherExpression>,
+    pub(crate) secret: Option<String>,
+    pub(crate) verification: Option<Verification>,
     #[serde(rename(deserialize = "actions-to-execute"))]
-    pub(crate) actions_to_execute: Vec<String>,
+    pub(crate) actions_to_execute: Option<Action>,
+    #[serde(default)]
+    pub(crate) actions: Vec<Step>,
+    #[serde(rename(deserialize = "pipe-body-to-stdin"), default)]
+    pub(crate) pipe_body_to_stdin: bool,
+    #[serde(default)]
+    pub(crate) concurrency: ConcurrencyPolicy,
+    pub(crate) retry: Option<RetryPolicy>,
+    /// The file defining the webhook, which can be the config file or an included file.
+    #[serde(skip_deserializing)]
+    pub(crate) source_file: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+/// What happens when a webhook is triggered while its actions are still queued or running.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub(crate) enum ConcurrencyPolicy {
+    /// Executions run at the same time.
+    #[serde(rename = "parallel")]
+    Parallel,
+    /// Executions wait for the previous ones to finish.
+    #[default]
+    #[serde(rename = "serial")]
+    Serial,
+    /// Previous executions are cancelled before the new one starts.
+    #[serde(rename = "cancel-previous")]
+    CancelPrevious,
+    /// The new execution is skipped.
+    #[serde(rename = "skip-if-running")]
+    SkipIfRunning,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
 pub(crate) enum MatchersStrategy {
+    #[default]
     #[serde(rename = "all")]
     All,
     #[serde(rename = "one")]
     One,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Clone)]
 pub(crate) struct Matcher {
     #[serde(rename(deserialize = "match-json-body"))]
     pub(crate) match_json_body: Option<serde_json::Value>,
+    #[serde(rename(deserialize = "json-compare-mode"), default)]
+    pub(crate) json_compare_mode: JsonCompareMode,
+    #[serde(rename(deserialize = "json-array-mode"), default)]
+    pub(crate) json_array_mode: JsonArrayMode,
     #[serde(rename(deserialize = "match-headers"))]
-    pub(crate) match_headers: Option<HashMap<String, String>>,
+    pub(crate) match_headers: Option<HashMap<String, HeaderValueMatcher>>,
+    #[serde(rename(deserialize = "match-json-path"))]
+    pub(crate) match_json_path: Option<Vec<JsonPathCondition>>,
+}
+
+/// How "match-json-body" compares the payload with the expected JSON.
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+pub(crate) enum JsonCompareMode {
+    /// The payload must be exactly equal to the expected JSON.
+    #[serde(rename = "strict")]
+    Strict,
+    /// The expected JSON must be a subset of the payload.
+    #[default]
+    #[serde(rename = "inclusive")]
+    Inclusive,
+}
+
+/// How arrays are compared in "inclusive" mode.
+#[derive(Debug, Default, Clone, Copy, Deserialize, PartialEq)]
+pub(crate) enum JsonArrayMode {
+    /// Each expected element must be included in one of the payload's elements, in any order.
+    #[default]
+    #[serde(rename = "any")]
+    Any,
+    /// Expected elements must be included in the payload's elements at the same positions.
+    #[serde(rename = "prefix")]
+    Prefix,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct Verification {
+    pub(crate) provider: VerificationProvider,
+    pub(crate) secret: String,
+    #[serde(rename(deserialize = "timestamp-tolerance"))]
+    pub(crate) timestamp_tolerance: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+pub(crate) enum VerificationProvider {
+    #[serde(rename = "github")]
+    Github,
+    #[serde(rename = "gitlab")]
+    Gitlab,
+    #[serde(rename = "gitea")]
+    Gitea,
+    #[serde(rename = "bitbucket")]
+    Bitbucket,
+    #[serde(rename = "stripe")]
+    Stripe,
 }
 
 impl Config {
@@ -45,9 +177,61 @@ impl Config {
     }
 }
 
-impl Default for MatchersStrategy {
-    fn default() -> Self {
-        MatchersStrategy::All
+impl Webhook {
+    /// The quoted name of the webhook followed by its file, for the errors and the logs.
+    pub(crate) fn label(&self) -> String {
+        match &self.source_file {
+            Some(source_file) => format!("\"{}\" (from {})", self.name, source_file),
+            None => format!("\"{}\"", self.name),
+        }
+    }
+
+    /// Webhooks configured with "matchers" are translated to an expression
+    /// when the config is loaded, but this is also done here for webhooks
+    /// that were not loaded with "get_config()".
+    pub(crate) fn matcher_expression(&self) -> Cow<'_, MatcherExpression> {
+        match &self.match_expression {
+            Some(expression) => Cow::Borrowed(expression),
+            None => Cow::Owned(MatcherExpression::from_legacy_matchers(
+                &self.matchers,
+                self.matchers_strategy.unwrap_or_default(),
+            )),
+        }
+    }
+
+    /// Like "matcher_expression()", the "actions-to-execute" option is
+    /// translated to a single step for webhooks that were not loaded with "get_config()".
+    pub(crate) fn steps(&self) -> Cow<'_, [Step]> {
+        match &self.actions_to_execute {
+            Some(action) if self.actions.is_empty() => Cow::Owned(vec![Step {
+                name: step::DEFAULT_STEP_NAME.to_string(),
+                run_if: RunIf::default(),
+                run: action.clone(),
+            }]),
+            _ => Cow::Borrowed(&self.actions),
+        }
+    }
+
+    /// The "secret" option is a shortcut for GitHub signatures,
+    /// the "verification" option has precedence over it.
+    pub(crate) fn signature_verification(&self) -> Option<Verification> {
+        if self.verification.is_some() {
+            return self.verification.clone();
+        }
+
+        self.secret.as_ref().map(|secret| Verification {
+            provider: VerificationProvider::Github,
+            secret: secret.clone(),
+            timestamp_tolerance: None,
+        })
+    }
+}
+
+impl Matcher {
+    pub(crate) fn has_conditions(&self) -> bool {
+        self.match_headers.is_some()
+            || self.match_json_body.is_some()
+            || self.match_json_path.is_some()
     }
 }
 
@@ -60,11 +244,36 @@ impl Display for MatchersStrategy {
     }
 }
 
-pub(crate) fn get_config_file(config_file: Option<&str>) -> Result<PathBuf, anyhow::Error> {
-    let default_file_name = format!(".{}.json", APPLICATION_NAME.to_ascii_lowercase());
-    let config_file_name = config_file.unwrap_or(default_file_name.as_str());
+/// The files searched in the current directory when no config file is specified,
+/// like ".pagoo.json" or ".pagoo.yaml".
+pub(crate) fn default_config_file_names() -> Vec<String> {
+    ConfigFormat::ALL
+        .iter()
+        .flat_map(|format| format.extensions())
+        .map(|extension| format!(".{}.{}", APPLICATION_NAME.to_ascii_lowercase(), extension))
+        .collect()
+}
 
-    let config_file_path = PathBuf::from(config_file_name);
+pub(crate) fn get_config_file(config_file: Option<&str>) -> Result<PathBuf, anyhow::Error> {
+    let default_file_names = default_config_file_names();
+
+    let config_file_path = match config_file {
+        Some(config_file_name) => PathBuf::from(config_file_name),
+        None => {
+            let mut existing_files = default_file_names
+                .iter()
+                .map(PathBuf::from)
+                .filter(|path| path.is_file());
+            let config_file_path = existing_files.next().unwrap_or_default();
+            if existing_files.next().is_some() {
+                warn!(
+                    "Several default config files exist, using \"{}\".",
+                    config_file_path.display()
+                );
+            }
+            config_file_path
+        }
+    };
 
     if !config_file_path.is_file() {
         return if config_file.is_some() {
@@ -73,25 +282,122 @@ pub(crate) fn get_config_file(config_file: Option<&str>) -> Result<PathBuf, anyh
                 config_file_path.to_str().unwrap()
             ))
         } else {
-            Err(anyhow::anyhow!("No config file specified, could not find a default one. You can create a \"{}\" file in this directory to configure the application.", default_file_name))
+            Err(anyhow::anyhow!("No config file specified, could not find a default one. You can create a \"{}\" file in this directory to configure the application.", default_file_names[0]))
         };
     }
 
     Ok(config_file_path)
 }
 
+/// The config is checked by the validator first, so that the server, the "validate" command
+/// and the reloads of the config apply the same rules. All the problems are in the error.
 pub(crate) fn get_config(config_file: Option<&str>) -> Result<Config, anyhow::Error> {
     let config_file_path = get_config_file(config_file)?;
 
-    let config_file_content = std::fs::read_to_string(&config_file_path)?;
+    let (errors, warnings): (Vec<Problem>, Vec<Problem>) =
+        validate_config_file(config_file_path.to_str())?
+            .into_iter()
+            .partition(Problem::is_error);
+    if !errors.is_empty() {
+        return Err(anyhow::anyhow!(
+            "The config file has {} error(s):\n{}",
+            errors.len(),
+            errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+                .join("\n")
+        ));
+    }
+    for warning in warnings {
+        warn!("{}", warning);
+    }
 
-    let mut config: Config = serde_json::from_str(&config_file_content)?;
+    let (value, mut secrets) = read_config_value(&config_file_path)?;
+    let mut config: Config = serde_json::from_value(value)?;
 
     config.config_file = config_file_path.to_str().unwrap().to_string();
+    for webhook in config.webhooks.iter_mut() {
+        webhook.source_file = Some(config.config_file.clone());
+    }
+
+    for included_file in
+        include::included_files(&config_file_path, &config.include).into_result()?
+    {
+        let source_file = included_file.to_str().unwrap().to_string();
+        let (value, included_secrets) = read_config_value(&included_file)?;
+        let included: include::IncludedFile = serde_json::from_value(value)
+            .map_err(|err| anyhow::anyhow!("Invalid included file \"{}\": {}", source_file, err))?;
+        secrets.extend(included_secrets);
+
+        for mut webhook in included.webhooks {
+            webhook.source_file = Some(source_file.clone());
+            config.webhooks.push(webhook);
+        }
+    }
+
+    // Webhooks are normalized, so that the rest of the application only uses
+    // the "actions" and "match" options.
+    for webhook in config.webhooks.iter_mut() {
+        webhook.actions = webhook.steps().into_owned();
+        webhook.actions_to_execute = None;
+        for step in webhook.actions.iter_mut() {
+            step.run.timeout = step.run.timeout.or(config.default_timeout);
+        }
+        webhook.match_expression = Some(webhook.matcher_expression().into_owned());
+    }
+
+    // Only a valid config replaces the secrets, so a failed reload keeps redacting the current ones.
+    crate::logging::set_secrets(secrets);
 
     Ok(config)
 }
 
+/// Reads only the "database_file" option, for the commands that do not need the webhooks,
+/// so that they work without the environment variables and secret files of the other options.
+pub(crate) fn get_database_file(config_file: Option<&str>) -> anyhow::Result<Option<String>> {
+    let config_file_path = get_config_file(config_file)?;
+    let content = std::fs::read_to_string(&config_file_path)?;
+
+    let value = ConfigFormat::from_path(&config_file_path)
+        .parse(&content)
+        .map_err(|err| {
+            anyhow::anyhow!(
+                "Could not parse \"{}\": {}",
+                config_file_path.display(),
+                err
+            )
+        })?;
+    let mut database_file = match value.get("database_file") {
+        Some(database_file) => database_file.clone(),
+        None => return Ok(None),
+    };
+
+    let base_dir = config_file_path.parent().unwrap_or(Path::new("."));
+    interpolation::interpolate(&mut database_file, base_dir).into_result()?;
+
+    match database_file {
+        serde_json::Value::Null => Ok(None),
+        serde_json::Value::String(database_file) => Ok(Some(database_file)),
+        _ => Err(anyhow::anyhow!(
+            "The \"database_file\" option must be a string."
+        )),
+    }
+}
+
+/// Parses and interpolates a config file, or an included file, with the secrets it contains.
+fn read_config_value(file: &Path) -> anyhow::Result<(serde_json::Value, Vec<Secret>)> {
+    let content = std::fs::read_to_string(file)?;
+
+    let mut value = ConfigFormat::from_path(file)
+        .parse(&content)
+        .map_err(|err| anyhow::anyhow!("Could not parse \"{}\": {}", file.display(), err))?;
+    let secrets = interpolation::interpolate(&mut value, file.parent().unwrap_or(Path::new(".")))
+        .into_result()?;
+
+    Ok((value, secrets))
+}
+
 pub(crate) fn pagoo_home_dir() -> PathBuf {
     let pagoo_home_dir = dirs::home_dir()
         .expect("Could not determine HOME_DIR to store database.")
@@ -105,6 +411,7 @@ pub(crate) fn pagoo_home_dir() -> PathBuf {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
     use std::path::PathBuf;
@@ -147,6 +454,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_webhook_without_actions() {
+        let config_file = std::env::temp_dir().join(format!("pagoo-{}.json", uuid::Uuid::new_v4()));
+        for webhook in [
+            r#"{"name": "empty", "matchers": [{"match-headers": {"x-event": "push"}}], "actions": []}"#,
+            r#"{"name": "empty", "matchers": [{"match-headers": {"x-event": "push"}}], "actions-to-execute": []}"#,
+            r#"{"name": "empty", "matchers": [{"match-headers": {"x-event": "push"}}]}"#,
+        ] {
+            std::fs::write(&config_file, format!(r#"{{"webhooks": [{}]}}"#, webhook)).unwrap();
+
+            let config = get_config(Some(config_file.to_str().unwrap()));
+
+            assert!(config.is_err(), "Config should be invalid: {}", webhook);
+        }
+        std::fs::remove_file(config_file).unwrap();
+    }
+
     #[test]
     fn test_config() {
         let sample_file = get_sample_file_path();
@@ -159,7 +483,8 @@ mod tests {
         assert_eq!(2, config.webhooks.len());
         let webhook = &config.webhooks[0];
         assert_eq!("my_webhook_name", webhook.name);
-        assert_eq!(vec!["echo", "success!"], webhook.actions_to_execute);
+        assert_eq!(1, webhook.actions.len());
+        assert_eq!(vec!["echo", "success!"], webhook.actions[0].run.argv());
         assert_eq!(true, webhook.matchers_strategy.is_some());
         assert_eq!(MatchersStrategy::One, webhook.matchers_strategy.unwrap());
 
@@ -187,8 +512,140 @@ mod tests {
         assert_eq!(true, headers.is_some());
         let headers_map = headers.unwrap();
         assert_eq!(true, headers_map.contains_key("x-github-event"));
-        assert_eq!("release", headers_map.get("x-github-event").unwrap());
+        assert_eq!(
+            &HeaderValueMatcher::Equals("release".to_string()),
+            headers_map.get("x-github-event").unwrap()
+        );
         assert_eq!(true, headers_map.contains_key("x-github-delivery"));
-        assert_eq!("12345", headers_map.get("x-github-delivery").unwrap());
+        assert_eq!(
+            &HeaderValueMatcher::Equals("12345".to_string()),
+            headers_map.get("x-github-delivery").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_included_webhooks() {
+        let dir = std::env::temp_dir().join(format!("pagoo-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("webhooks.d")).unwrap();
+        let config_file = dir.join("pagoo.json");
+        let webhook = |name: &str| {
+            format!(
+                r#"{{"name": "{}", "match": {{"match-headers": {{"x-event": "push"}}}}, "actions-to-execute": ["true"]}}"#,
+                name
+            )
+        };
+        std::fs::write(
+            &config_file,
+            format!(
+                r#"{{"include": ["webhooks.d"], "webhooks": [{}]}}"#,
+                webhook("main")
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("webhooks.d/deploy.json"),
+            format!(r#"{{"webhooks": [{}]}}"#, webhook("deploy")),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("webhooks.d/build.yaml"),
+            "webhooks:\n  - name: build\n    match: {match-headers: {x-event: push}}\n    actions-to-execute: [\"true\"]\n",
+        )
+        .unwrap();
+
+        let config = get_config(Some(config_file.to_str().unwrap())).unwrap();
+
+        let names: Vec<&str> = config.webhooks.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(vec!["main", "build", "deploy"], names);
+        assert_eq!(
+            format!(
+                "\"deploy\" (from {})",
+                dir.join("webhooks.d/deploy.json").display()
+            ),
+            config.webhooks[2].label()
+        );
+
+        std::fs::write(
+            dir.join("webhooks.d/other.json"),
+            format!(r#"{{"webhooks": [{}]}}"#, webhook("main")),
+        )
+        .unwrap();
+
+        let err = get_config(Some(config_file.to_str().unwrap())).unwrap_err();
+
+        assert_eq!(
+            format!(
+                "The config file has 1 error(s):\n{}:1:24: $.webhooks[0].name: Webhook name \"main\" is already used by $.webhooks[0] in {}.",
+                dir.join("webhooks.d/other.json").display(),
+                config_file.display()
+            ),
+            err.to_string()
+        );
+
+        std::fs::write(
+            dir.join("webhooks.d/other.json"),
+            r#"{"include": ["*.json"], "webhooks": []}"#,
+        )
+        .unwrap();
+
+        let err = get_config(Some(config_file.to_str().unwrap())).unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("Included files can only contain a \"webhooks\" list."));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_database_file() {
+        let dir = std::env::temp_dir().join(format!("pagoo-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_file = dir.join("pagoo.json");
+        std::env::set_var("PAGOO_TEST_DATABASE_DIR", "/var/lib/pagoo");
+        std::fs::write(
+            &config_file,
+            r#"{"database_file": "${PAGOO_TEST_DATABASE_DIR}/pagoo.db3", "include": ["missing.json"], "webhooks": [{"name": "deploy", "secret": "${PAGOO_TEST_UNSET_SECRET}"}]}"#,
+        )
+        .unwrap();
+
+        assert!(get_config(Some(config_file.to_str().unwrap())).is_err());
+        assert_eq!(
+            Some("/var/lib/pagoo/pagoo.db3".to_string()),
+            get_database_file(Some(config_file.to_str().unwrap())).unwrap()
+        );
+
+        std::fs::write(&config_file, r#"{"webhooks": []}"#).unwrap();
+
+        assert_eq!(
+            None,
+            get_database_file(Some(config_file.to_str().unwrap())).unwrap()
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_signature_verification() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "webhooks": [
+                    {"name": "legacy", "secret": "s3cr3t", "matchers": [], "actions-to-execute": ["true"]},
+                    {"name": "stripe", "verification": {"provider": "stripe", "secret": "whsec", "timestamp-tolerance": 60}, "matchers": [], "actions-to-execute": ["true"]},
+                    {"name": "unsigned", "matchers": [], "actions-to-execute": ["true"]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let verification = config.webhooks[0].signature_verification().unwrap();
+        assert_eq!(VerificationProvider::Github, verification.provider);
+        assert_eq!("s3cr3t", verification.secret);
+
+        let verification = config.webhooks[1].signature_verification().unwrap();
+        assert_eq!(VerificationProvider::Stripe, verification.provider);
+        assert_eq!(Some(60), verification.timestamp_tolerance);
+
+        assert!(config.webhooks[2].signature_verification().is_none());
     }
 }
@@ -0,0 +1,139 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::time::Duration;
+
+/// How failed actions of a webhook are executed again.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RetryPolicy {
+    /// Including the first execution.
+    #[serde(rename = "max-attempts")]
+    pub(crate) max_attempts: u32,
+    /// In seconds.
+    #[serde(rename = "initial-delay", default = "default_initial_delay")]
+    pub(crate) initial_delay: u64,
+    #[serde(default = "default_multiplier")]
+    pub(crate) multiplier: f64,
+    /// In seconds.
+    #[serde(rename = "max-delay", default = "default_max_delay")]
+    pub(crate) max_delay: u64,
+    /// Exit codes that are retried. When empty, all failures are retried, including timeouts.
+    #[serde(rename = "retry-on", default)]
+    pub(crate) retry_on: Vec<i32>,
+}
+
+fn default_initial_delay() -> u64 {
+    1
+}
+
+fn default_multiplier() -> f64 {
+    2.0
+}
+
+fn default_max_delay() -> u64 {
+    300
+}
+
+impl RetryPolicy {
+    /// The invalid option, with the reason why it is invalid.
+    pub(crate) fn problem(&self) -> Option<(&'static str, &'static str)> {
+        if self.max_attempts == 0 {
+            return Some((
+                "max-attempts",
+                "The \"max-attempts\" retry option must be greater than 0.",
+            ));
+        }
+
+        // Delays must not decrease, and NaN or infinite delays cannot be waited for.
+        if !self.multiplier.is_finite() || self.multiplier < 1.0 {
+            return Some((
+                "multiplier",
+                "The \"multiplier\" retry option must be a number greater than or equal to 1.",
+            ));
+        }
+
+        None
+    }
+
+    /// Whether the failed "attempt" (starting at 1) must be retried.
+    pub(crate) fn should_retry(&self, attempt: u32, exit_code: Option<i32>) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+
+        self.retry_on.is_empty()
+            || exit_code.is_some_and(|exit_code| self.retry_on.contains(&exit_code))
+    }
+
+    /// The delay to wait after the failed "attempt" (starting at 1).
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial_delay as f64 * self.multiplier.powi(exponent);
+
+        Duration::from_secs_f64(delay.min(self.max_delay as f64).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_policy(json: &str) -> RetryPolicy {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_exponential_delay() {
+        let policy = get_policy(r#"{"max-attempts": 10, "initial-delay": 2, "max-delay": 20}"#);
+
+        assert_eq!(Duration::from_secs(2), policy.delay(1));
+        assert_eq!(Duration::from_secs(4), policy.delay(2));
+        assert_eq!(Duration::from_secs(8), policy.delay(3));
+        assert_eq!(Duration::from_secs(20), policy.delay(5));
+        assert_eq!(Duration::from_secs(20), policy.delay(u32::MAX));
+    }
+
+    #[test]
+    fn test_invalid_multiplier() {
+        assert_eq!(
+            Some("multiplier"),
+            get_policy(r#"{"max-attempts": 3, "multiplier": -2}"#)
+                .problem()
+                .map(|(option, _)| option)
+        );
+        assert_eq!(
+            Some("multiplier"),
+            get_policy(r#"{"max-attempts": 3, "multiplier": 0.5}"#)
+                .problem()
+                .map(|(option, _)| option)
+        );
+        assert_eq!(
+            None,
+            get_policy(r#"{"max-attempts": 3, "multiplier": 1}"#).problem()
+        );
+
+        let policy = RetryPolicy {
+            multiplier: -2.0,
+            ..get_policy(r#"{"max-attempts": 3}"#)
+        };
+        assert_eq!(Duration::ZERO, policy.delay(2));
+    }
+
+    #[test]
+    fn test_max_attempts() {
+        let policy = get_policy(r#"{"max-attempts": 3}"#);
+
+        assert!(policy.should_retry(1, Some(1)));
+        assert!(policy.should_retry(2, None));
+        assert!(!policy.should_retry(3, Some(1)));
+    }
+
+    #[test]
+    fn test_retry_on_exit_codes() {
+        let policy = get_policy(r#"{"max-attempts": 3, "retry-on": [128, 255]}"#);
+
+        assert!(policy.should_retry(1, Some(128)));
+        assert!(!policy.should_retry(1, Some(1)));
+        assert!(!policy.should_retry(1, None));
+    }
+}
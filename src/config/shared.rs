@@ -0,0 +1,57 @@
+use crate::config::Config;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+/// The active config of the server, which is replaced when the config file is reloaded.
+///
+/// Requests use the config that was active when they were received until they are handled,
+/// and jobs keep a copy of the webhook they matched, so a reload never changes them.
+#[derive(Debug, Default)]
+pub(crate) struct SharedConfig {
+    config: RwLock<Arc<Config>>,
+}
+
+impl SharedConfig {
+    pub(crate) fn new(config: Config) -> Self {
+        SharedConfig {
+            config: RwLock::new(Arc::new(config)),
+        }
+    }
+
+    pub(crate) fn get(&self) -> Arc<Config> {
+        Arc::clone(&self.config.read().expect("Could not read the config."))
+    }
+
+    /// Returns the previous config.
+    pub(crate) fn replace(&self, config: Config) -> Arc<Config> {
+        let mut active_config = self.config.write().expect("Could not replace the config.");
+
+        std::mem::replace(&mut active_config, Arc::new(config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace() {
+        let config = Config {
+            workers: Some(1),
+            ..Default::default()
+        };
+        let shared_config = SharedConfig::new(config);
+
+        let active_config = shared_config.get();
+
+        let config = Config {
+            workers: Some(2),
+            ..Default::default()
+        };
+        let previous_config = shared_config.replace(config);
+
+        assert_eq!(Some(1), active_config.workers);
+        assert_eq!(Some(1), previous_config.workers);
+        assert_eq!(Some(2), shared_config.get().workers);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::config::Action;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Name of the step created from the "actions-to-execute" option.
+pub(crate) const DEFAULT_STEP_NAME: &str = "main";
+
+/// A named action of a webhook's pipeline.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Step {
+    pub(crate) name: String,
+    #[serde(rename = "run-if", default)]
+    pub(crate) run_if: RunIf,
+    pub(crate) run: Action,
+}
+
+/// When a step is executed, depending on the result of the previous steps.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub(crate) enum RunIf {
+    /// All the previous steps succeeded.
+    #[default]
+    #[serde(rename = "success")]
+    Success,
+    /// One of the previous steps failed.
+    #[serde(rename = "failure")]
+    Failure,
+    #[serde(rename = "always")]
+    Always,
+}
+
+impl RunIf {
+    pub(crate) fn should_run(&self, previous_step_failed: bool) -> bool {
+        match self {
+            RunIf::Success => !previous_step_failed,
+            RunIf::Failure => previous_step_failed,
+            RunIf::Always => true,
+        }
+    }
+}
+
+/// Step names are used in "{{ steps.<name>.stdout }}" placeholders.
+pub(crate) fn validate_steps(steps: &[Step]) -> anyhow::Result<()> {
+    for (index, step) in steps.iter().enumerate() {
+        let is_valid_name = !step.name.is_empty()
+            && step
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !is_valid_name {
+            return Err(anyhow::anyhow!(
+                "Step name \"{}\" must only contain letters, digits, \"_\" and \"-\".",
+                step.name
+            ));
+        }
+
+        if steps[..index].iter().any(|other| other.name == step.name) {
+            return Err(anyhow::anyhow!(
+                "Step name \"{}\" is used more than once.",
+                step.name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_if() {
+        assert!(RunIf::Success.should_run(false));
+        assert!(!RunIf::Success.should_run(true));
+        assert!(!RunIf::Failure.should_run(false));
+        assert!(RunIf::Failure.should_run(true));
+        assert!(RunIf::Always.should_run(false));
+        assert!(RunIf::Always.should_run(true));
+    }
+
+    #[test]
+    fn test_validate_steps() {
+        let steps: Vec<Step> = serde_json::from_str(
+            r#"[
+                {"name": "pull", "run": ["git", "pull"]},
+                {"name": "notify-failure", "run-if": "failure", "run": {"command": "notify.sh"}}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(RunIf::Failure, steps[1].run_if);
+        assert!(validate_steps(&steps).is_ok());
+
+        let steps: Vec<Step> = serde_json::from_str(
+            r#"[{"name": "build", "run": ["make"]}, {"name": "build", "run": ["make", "test"]}]"#,
+        )
+        .unwrap();
+        assert!(validate_steps(&steps).is_err());
+
+        let steps: Vec<Step> =
+            serde_json::from_str(r#"[{"name": "build.release", "run": ["make"]}]"#).unwrap();
+        assert!(validate_steps(&steps).is_err());
+    }
+}
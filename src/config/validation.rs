@@ -0,0 +1,1026 @@
+use crate::config::format;
+use crate::config::get_config_file;
+use crate::config::include;
+use crate::config::interpolation;
+use crate::config::step;
+use crate::config::Action;
+use crate::config::ConcurrencyPolicy;
+use crate::config::ConfigFormat;
+use crate::config::HeaderValueMatcher;
+use crate::config::JsonArrayMode;
+use crate::config::JsonCompareMode;
+use crate::config::JsonPathCondition;
+use crate::config::Matcher;
+use crate::config::MatcherExpression;
+use crate::config::MatchersStrategy;
+use crate::config::RetryPolicy;
+use crate::config::Step;
+use crate::config::Verification;
+use crate::config::Webhook;
+use serde::de::DeserializeOwned;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// A problem found in the config file or in an included file, with the JSON path
+/// of the faulty value and its line and column in the file, both starting at 1.
+/// Positions are only known for syntax errors in YAML and TOML files.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Problem {
+    pub(crate) file: String,
+    pub(crate) path: String,
+    pub(crate) position: Option<(usize, usize)>,
+    pub(crate) message: String,
+    pub(crate) severity: Severity,
+}
+
+/// Errors prevent the config from being loaded. Warnings depend on the machine
+/// the config is checked on, like the programs installed in the "PATH".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+type Drill<'a> = fn(&mut Validator<'a>, &Value, &[PathSegment]);
+
+/// Unlike "get_config()", which stops at the first error, the validator
+/// collects all the problems of the config file and of the included files.
+struct Validator<'a> {
+    file: &'a Path,
+    content: &'a str,
+    format: ConfigFormat,
+    problems: Vec<Problem>,
+    /// The file and the path of the first webhook using each name, shared by all the files.
+    names: HashMap<String, (String, Vec<PathSegment>)>,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "",
+            Severity::Warning => "warning: ",
+        };
+
+        match self.position {
+            Some((line, column)) => write!(
+                f,
+                "{}:{}:{}: {}: {}{}",
+                self.file, line, column, self.path, severity, self.message
+            ),
+            None => write!(
+                f,
+                "{}: {}: {}{}",
+                self.file, self.path, severity, self.message
+            ),
+        }
+    }
+}
+
+impl Problem {
+    pub(crate) fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+pub(crate) fn validate_config_file(config_file: Option<&str>) -> anyhow::Result<Vec<Problem>> {
+    let config_file_path = get_config_file(config_file)?;
+
+    let config_file_content = std::fs::read_to_string(&config_file_path)?;
+
+    Ok(validate(&config_file_path, &config_file_content))
+}
+
+/// Validates the content of the config file, then the files it includes.
+/// Relative secret files and included files are searched from the directory of each file.
+pub(crate) fn validate(config_file: &Path, content: &str) -> Vec<Problem> {
+    let mut validator = Validator::new(config_file, content, Vec::new(), HashMap::new());
+
+    let included_files = match validator.parse() {
+        Some(value) => {
+            validator.validate_config(&value);
+            match value.as_object() {
+                Some(config) => validator.validate_includes(config),
+                None => Vec::new(),
+            }
+        }
+        None => Vec::new(),
+    };
+
+    let (mut problems, mut names) = (validator.problems, validator.names);
+    for included_file in included_files {
+        let content = match std::fs::read_to_string(&included_file) {
+            Ok(content) => content,
+            Err(err) => {
+                problems.push(Problem {
+                    file: included_file.display().to_string(),
+                    path: format_path(&[]),
+                    position: None,
+                    message: format!("Could not read the included file: {}", err),
+                    severity: Severity::Error,
+                });
+                continue;
+            }
+        };
+
+        let mut validator = Validator::new(&included_file, &content, problems, names);
+        if let Some(value) = validator.parse() {
+            validator.validate_included_file(&value);
+        }
+        (problems, names) = (validator.problems, validator.names);
+    }
+
+    problems
+}
+
+impl<'a> Validator<'a> {
+    fn new(
+        file: &'a Path,
+        content: &'a str,
+        problems: Vec<Problem>,
+        names: HashMap<String, (String, Vec<PathSegment>)>,
+    ) -> Self {
+        Validator {
+            file,
+            content,
+            format: ConfigFormat::from_path(file),
+            problems,
+            names,
+        }
+    }
+
+    /// Parses and interpolates the file, the value is returned even with interpolation problems.
+    fn parse(&mut self) -> Option<Value> {
+        match self.format.parse(self.content) {
+            Ok(mut value) => {
+                let base_dir = self.file.parent().unwrap_or(Path::new("."));
+                for (path, message) in interpolation::interpolate(&mut value, base_dir).errors {
+                    self.push(&path, message);
+                }
+                Some(value)
+            }
+            Err(err) => {
+                self.problems.push(Problem {
+                    file: self.file.display().to_string(),
+                    path: format_path(&[]),
+                    position: err.position,
+                    message: err.message,
+                    severity: Severity::Error,
+                });
+                None
+            }
+        }
+    }
+
+    fn push(&mut self, path: &[PathSegment], message: impl Into<String>) {
+        self.report(path, message, Severity::Error);
+    }
+
+    fn warn(&mut self, path: &[PathSegment], message: impl Into<String>) {
+        self.report(path, message, Severity::Warning);
+    }
+
+    fn report(&mut self, path: &[PathSegment], message: impl Into<String>, severity: Severity) {
+        let position = match self.format {
+            ConfigFormat::Json | ConfigFormat::Json5 => locate(self.content, path),
+            ConfigFormat::Yaml | ConfigFormat::Toml => None,
+        };
+
+        self.problems.push(Problem {
+            file: self.file.display().to_string(),
+            path: format_path(path),
+            position,
+            message: message.into(),
+            severity,
+        });
+    }
+
+    /// When the value cannot be deserialized, "drill" looks for the problem in its children,
+    /// so that it is reported with the most precise path.
+    fn deserialize<T: DeserializeOwned>(
+        &mut self,
+        value: &Value,
+        path: &[PathSegment],
+        drill: Drill<'a>,
+    ) -> Option<T> {
+        match T::deserialize(value) {
+            Ok(deserialized) => Some(deserialized),
+            Err(err) => {
+                let problems = self.problems.len();
+                drill(self, value, path);
+                if self.problems.len() == problems {
+                    self.push(path, err.to_string());
+                }
+                None
+            }
+        }
+    }
+
+    fn check<T: DeserializeOwned>(&mut self, value: &Value, path: &[PathSegment]) {
+        self.deserialize::<T>(value, path, |_, _, _| {});
+    }
+
+    fn check_each<T: DeserializeOwned>(
+        &mut self,
+        value: &Value,
+        path: &[PathSegment],
+        drill: Drill<'a>,
+    ) {
+        match value.as_array() {
+            Some(elements) => {
+                for (index, element) in elements.iter().enumerate() {
+                    let element_path = child_path(path, PathSegment::Index(index));
+                    self.deserialize::<T>(element, &element_path, drill);
+                }
+            }
+            None => self.check::<Vec<T>>(value, path),
+        }
+    }
+
+    fn validate_config(&mut self, value: &Value) {
+        let root = Vec::new();
+        let config = match value.as_object() {
+            Some(config) => config,
+            None => return self.push(&root, "The config must be a JSON object."),
+        };
+
+        self.check_field::<String>(config, &root, "database_file");
+        self.check_field::<u64>(config, &root, "default-timeout");
+        if self.check_field::<usize>(config, &root, "workers") == Some(0) {
+            self.push(
+                &child_path(&root, key("workers")),
+                "The \"workers\" option must be greater than 0.",
+            );
+        }
+        if self
+            .check_field::<String>(config, &root, "api-token")
+            .is_some_and(|token| token.is_empty())
+        {
+            self.push(
+                &child_path(&root, key("api-token")),
+                "The \"api-token\" option cannot be empty.",
+            );
+        }
+
+        if !config.contains_key("webhooks") && !config.contains_key("include") {
+            return self.push(&root, "The \"webhooks\" option is missing.");
+        }
+        self.validate_webhooks(config);
+    }
+
+    /// Returns the included files, the problems of the "include" patterns are reported at their index.
+    fn validate_includes(&mut self, config: &Map<String, Value>) -> Vec<PathBuf> {
+        let patterns = match self.check_field::<Vec<String>>(config, &[], "include") {
+            Some(patterns) => patterns,
+            None => return Vec::new(),
+        };
+
+        let includes = include::included_files(self.file, &patterns);
+        for (index, message) in includes.errors {
+            self.push(&[key("include"), PathSegment::Index(index)], message);
+        }
+
+        includes.files
+    }
+
+    fn validate_included_file(&mut self, value: &Value) {
+        let root = Vec::new();
+        let included_file = match value.as_object() {
+            Some(included_file) => included_file,
+            None => return self.push(&root, "An included file must be a JSON object."),
+        };
+
+        for name in included_file.keys().filter(|name| *name != "webhooks") {
+            self.push(
+                &child_path(&root, key(name)),
+                "Included files can only contain a \"webhooks\" list.",
+            );
+        }
+        if !included_file.contains_key("webhooks") {
+            return self.push(&root, "The \"webhooks\" option is missing.");
+        }
+        self.validate_webhooks(included_file);
+    }
+
+    fn validate_webhooks(&mut self, config: &Map<String, Value>) {
+        let webhooks = match config.get("webhooks") {
+            Some(Value::Array(webhooks)) => webhooks,
+            Some(_) => {
+                return self.push(
+                    &[key("webhooks")],
+                    "The \"webhooks\" option must be a list.",
+                )
+            }
+            None => return,
+        };
+
+        let file = self.file.display().to_string();
+        for (index, value) in webhooks.iter().enumerate() {
+            let path = vec![key("webhooks"), PathSegment::Index(index)];
+            // Names are checked even for invalid webhooks, which are still registered.
+            if let Some(name) = value.get("name").and_then(Value::as_str) {
+                match self.names.get(name) {
+                    Some((first_file, first_path)) => {
+                        let first_webhook = match *first_file == file {
+                            true => format_path(first_path),
+                            false => format!("{} in {}", format_path(first_path), first_file),
+                        };
+                        self.push(
+                            &child_path(&path, key("name")),
+                            format!(
+                                "Webhook name \"{}\" is already used by {}.",
+                                name, first_webhook
+                            ),
+                        )
+                    }
+                    None => {
+                        self.names
+                            .insert(name.to_string(), (file.clone(), path.clone()));
+                    }
+                }
+            }
+
+            let webhook = match self.deserialize::<Webhook>(value, &path, Validator::drill_webhook)
+            {
+                Some(webhook) => webhook,
+                None => continue,
+            };
+
+            self.validate_actions(&webhook, &path);
+            self.validate_matchers(&webhook, &path);
+
+            if let Some((option, message)) = webhook.retry.as_ref().and_then(RetryPolicy::problem) {
+                self.push(&[path, vec![key("retry"), key(option)]].concat(), message);
+            }
+        }
+    }
+
+    fn check_field<T: DeserializeOwned>(
+        &mut self,
+        object: &Map<String, Value>,
+        path: &[PathSegment],
+        name: &str,
+    ) -> Option<T> {
+        let value = object.get(name)?;
+
+        self.deserialize::<T>(value, &child_path(path, key(name)), |_, _, _| {})
+    }
+
+    fn validate_actions(&mut self, webhook: &Webhook, path: &[PathSegment]) {
+        if webhook.actions_to_execute.is_some() && !webhook.actions.is_empty() {
+            return self.push(
+                &child_path(path, key("actions")),
+                "A webhook cannot use both the \"actions\" and the \"actions-to-execute\" options.",
+            );
+        }
+
+        let steps = webhook.steps();
+        if steps.is_empty() {
+            return self.push(
+                path,
+                "A webhook must have an \"actions-to-execute\" option or a non-empty \"actions\" list.",
+            );
+        }
+
+        if let Err(err) = step::validate_steps(&steps) {
+            self.push(&child_path(path, key("actions")), err.to_string());
+        }
+
+        for (index, step) in steps.iter().enumerate() {
+            let action_path = match webhook.actions_to_execute {
+                Some(_) => child_path(path, key("actions-to-execute")),
+                None => [
+                    path,
+                    &[key("actions"), PathSegment::Index(index), key("run")],
+                ]
+                .concat(),
+            };
+
+            if let Err(err) = crate::actions::executor::validate_action(&step.run) {
+                self.push(&action_path, err.to_string());
+            }
+            if let Err(message) = crate::actions::templating::validate_placeholders(&step.run) {
+                self.push(&action_path, message);
+            }
+            if let Err(message) = find_program(&step.run) {
+                self.warn(&action_path, message);
+            }
+        }
+    }
+
+    fn validate_matchers(&mut self, webhook: &Webhook, path: &[PathSegment]) {
+        if webhook.match_expression.is_some() {
+            if !webhook.matchers.is_empty() || webhook.matchers_strategy.is_some() {
+                self.push(
+                    &child_path(path, key("match")),
+                    "A webhook cannot use both the \"match\" and the \"matchers\" options.",
+                );
+            }
+        } else if webhook.matchers.is_empty() {
+            return self.push(
+                path,
+                "A webhook must have a \"match\" expression or a non-empty \"matchers\" list.",
+            );
+        }
+
+        let problems = self.problems.len();
+        for (index, matcher) in webhook.matchers.iter().enumerate() {
+            if !matcher.has_conditions() {
+                self.push(
+                    &[path, &[key("matchers"), PathSegment::Index(index)]].concat(),
+                    "A matcher must contain at least one of the \"match-headers\", \"match-json-body\" or \"match-json-path\" keys, otherwise it never matches.",
+                );
+            }
+        }
+
+        if self.problems.len() == problems && !can_match(&webhook.matcher_expression()) {
+            let option = match webhook.match_expression {
+                Some(_) => "match",
+                None => "matchers",
+            };
+            self.push(
+                &child_path(path, key(option)),
+                "The webhook is unreachable, no request can match it.",
+            );
+        }
+    }
+
+    fn drill_webhook(&mut self, value: &Value, path: &[PathSegment]) {
+        let webhook = match value.as_object() {
+            Some(webhook) => webhook,
+            None => return,
+        };
+
+        for (name, value) in webhook {
+            let path = child_path(path, key(name));
+            match name.as_str() {
+                "name" | "secret" => self.check::<String>(value, &path),
+                "matchers-strategy" => self.check::<MatchersStrategy>(value, &path),
+                "matchers" => self.check_each::<Matcher>(value, &path, Validator::drill_matcher),
+                "match" => {
+                    self.deserialize::<MatcherExpression>(
+                        value,
+                        &path,
+                        Validator::drill_expression,
+                    );
+                }
+                "verification" => self.check::<Verification>(value, &path),
+                "actions-to-execute" => self.check::<Action>(value, &path),
+                "actions" => self.check_each::<Step>(value, &path, |_, _, _| {}),
+                "pipe-body-to-stdin" => self.check::<bool>(value, &path),
+                "concurrency" => self.check::<ConcurrencyPolicy>(value, &path),
+                "retry" => self.check::<RetryPolicy>(value, &path),
+                _ => {}
+            }
+        }
+    }
+
+    fn drill_expression(&mut self, value: &Value, path: &[PathSegment]) {
+        let group = match value.as_object() {
+            Some(group) if ["all", "any", "not"].iter().any(|k| group.contains_key(*k)) => group,
+            _ => return self.drill_matcher(value, path),
+        };
+
+        for (name, value) in group {
+            let path = child_path(path, key(name));
+            match name.as_str() {
+                "all" | "any" => {
+                    self.check_each::<MatcherExpression>(value, &path, Validator::drill_expression)
+                }
+                "not" => {
+                    self.deserialize::<MatcherExpression>(
+                        value,
+                        &path,
+                        Validator::drill_expression,
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn drill_matcher(&mut self, value: &Value, path: &[PathSegment]) {
+        let matcher = match value.as_object() {
+            Some(matcher) => matcher,
+            None => return,
+        };
+
+        for (name, value) in matcher {
+            let path = child_path(path, key(name));
+            match (name.as_str(), value) {
+                ("match-headers", Value::Object(headers)) => {
+                    for (header, value) in headers {
+                        self.check::<HeaderValueMatcher>(value, &child_path(&path, key(header)));
+                    }
+                }
+                ("match-headers", _) => {
+                    self.check::<HashMap<String, HeaderValueMatcher>>(value, &path)
+                }
+                ("match-json-path", _) => {
+                    self.check_each::<JsonPathCondition>(value, &path, |_, _, _| {})
+                }
+                ("json-compare-mode", _) => self.check::<JsonCompareMode>(value, &path),
+                ("json-array-mode", _) => self.check::<JsonArrayMode>(value, &path),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Whether some request can match the expression. The conditions of a matcher are
+/// considered satisfiable, so only empty groups and their negations are detected.
+fn can_match(expression: &MatcherExpression) -> bool {
+    match expression {
+        MatcherExpression::All(expressions) => expressions.iter().all(can_match),
+        MatcherExpression::Any(expressions) => expressions.iter().any(can_match),
+        MatcherExpression::Not(expression) => !always_matches(expression),
+        MatcherExpression::Matcher(_) => true,
+    }
+}
+
+fn always_matches(expression: &MatcherExpression) -> bool {
+    match expression {
+        MatcherExpression::All(expressions) => expressions.iter().all(always_matches),
+        MatcherExpression::Any(expressions) => expressions.iter().any(always_matches),
+        MatcherExpression::Not(expression) => !can_match(expression),
+        MatcherExpression::Matcher(_) => false,
+    }
+}
+
+/// Looks for the program of the action like the executor does: paths are relative
+/// to the working directory, other programs are searched in the "PATH" given to the action.
+fn find_program(action: &Action) -> Result<(), String> {
+    let program = action.argv().remove(0);
+
+    if program.contains(std::path::MAIN_SEPARATOR) {
+        let program_path = match &action.cwd {
+            Some(cwd) => cwd.join(&program),
+            None => Path::new(&program).to_path_buf(),
+        };
+        return match program_path.is_file() {
+            true => Ok(()),
+            false => Err(format!("Program \"{}\" does not exist.", program)),
+        };
+    }
+
+    let search_path = action
+        .env
+        .get("PATH")
+        .cloned()
+        .or_else(|| std::env::var("PATH").ok())
+        .unwrap_or_default();
+
+    match std::env::split_paths(&search_path).any(|directory| directory.join(&program).is_file()) {
+        true => Ok(()),
+        false => Err(format!(
+            "Program \"{}\" was not found in the PATH.",
+            program
+        )),
+    }
+}
+
+fn key(name: &str) -> PathSegment {
+    PathSegment::Key(name.to_string())
+}
+
+fn child_path(path: &[PathSegment], segment: PathSegment) -> Vec<PathSegment> {
+    let mut path = path.to_vec();
+    path.push(segment);
+
+    path
+}
+
+/// Keys that are not plain identifiers use the bracket notation, like "$.headers[\"x.y\"]".
+pub(super) fn format_path(path: &[PathSegment]) -> String {
+    let mut formatted = "$".to_string();
+
+    for segment in path {
+        match segment {
+            PathSegment::Key(name)
+                if !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+            {
+                formatted.push('.');
+                formatted.push_str(name);
+            }
+            PathSegment::Key(name) => {
+                formatted.push_str(&format!("[{}]", Value::String(name.clone())))
+            }
+            PathSegment::Index(index) => formatted.push_str(&format!("[{}]", index)),
+        }
+    }
+
+    formatted
+}
+
+/// Returns the line and column of the value at "path" in the JSON or JSON5 content.
+fn locate(content: &str, path: &[PathSegment]) -> Option<(usize, usize)> {
+    let offset = find_value(content.as_bytes(), 0, path)?;
+
+    Some(format::line_and_column(content, offset))
+}
+
+fn find_value(bytes: &[u8], position: usize, path: &[PathSegment]) -> Option<usize> {
+    let mut position = skip_whitespace(bytes, position);
+
+    let (segment, path) = match path.split_first() {
+        Some(split) => split,
+        None => return Some(position),
+    };
+
+    match segment {
+        PathSegment::Key(name) => {
+            if bytes.get(position) != Some(&b'{') {
+                return None;
+            }
+            position += 1;
+            loop {
+                position = skip_whitespace(bytes, position);
+                let (key, key_end) = parse_key(bytes, position)?;
+
+                position = skip_whitespace(bytes, key_end);
+                if bytes.get(position) != Some(&b':') {
+                    return None;
+                }
+                position += 1;
+                if &key == name {
+                    return find_value(bytes, position, path);
+                }
+
+                position = skip_separator(bytes, skip_value(bytes, position)?)?;
+            }
+        }
+        PathSegment::Index(index) => {
+            if bytes.get(position) != Some(&b'[') {
+                return None;
+            }
+            position += 1;
+            for _ in 0..*index {
+                position = skip_separator(bytes, skip_value(bytes, position)?)?;
+            }
+            find_value(bytes, position, path)
+        }
+    }
+}
+
+/// Keys of JSON5 objects can be quoted with double quotes, or be identifiers.
+fn parse_key(bytes: &[u8], position: usize) -> Option<(String, usize)> {
+    let end = match bytes.get(position)? {
+        b'"' => skip_value(bytes, position)?,
+        _ => {
+            let mut end = position;
+            while bytes
+                .get(end)
+                .is_some_and(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'$'))
+            {
+                end += 1;
+            }
+            return (end > position).then(|| {
+                (
+                    String::from_utf8_lossy(&bytes[position..end]).to_string(),
+                    end,
+                )
+            });
+        }
+    };
+
+    Some((serde_json::from_slice(&bytes[position..end]).ok()?, end))
+}
+
+/// Also skips the comments of JSON5 and JSONC files.
+fn skip_whitespace(bytes: &[u8], mut position: usize) -> usize {
+    loop {
+        match (bytes.get(position), bytes.get(position + 1)) {
+            (Some(b), _) if b.is_ascii_whitespace() => position += 1,
+            (Some(b'/'), Some(b'/')) => {
+                while bytes.get(position).is_some_and(|b| *b != b'\n') {
+                    position += 1;
+                }
+            }
+            (Some(b'/'), Some(b'*')) => {
+                position += 2;
+                while bytes.get(position).is_some() && !bytes[position..].starts_with(b"*/") {
+                    position += 1;
+                }
+                position += 2;
+            }
+            _ => return position.min(bytes.len()),
+        }
+    }
+}
+
+/// Skips the comma following a value, fails at the end of an object or an array.
+fn skip_separator(bytes: &[u8], position: usize) -> Option<usize> {
+    let position = skip_whitespace(bytes, position);
+
+    match bytes.get(position) {
+        Some(b',') => Some(position + 1),
+        _ => None,
+    }
+}
+
+/// Returns the position following the value starting at "position".
+fn skip_value(bytes: &[u8], position: usize) -> Option<usize> {
+    let position = skip_whitespace(bytes, position);
+
+    match bytes.get(position)? {
+        quote @ (b'"' | b'\'') => {
+            let mut position = position + 1;
+            loop {
+                match bytes.get(position)? {
+                    b'\\' => position += 2,
+                    b if b == quote => return Some(position + 1),
+                    _ => position += 1,
+                }
+            }
+        }
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut position = position;
+            loop {
+                match bytes.get(position)? {
+                    b'"' | b'\'' | b'/' => {
+                        position = skip_value(bytes, position)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(position + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                position += 1;
+            }
+        }
+        _ => {
+            let mut position = position;
+            while bytes
+                .get(position)
+                .is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace())
+            {
+                position += 1;
+            }
+            Some(position)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(problems: &[Problem]) -> Vec<(String, usize, usize)> {
+        problems
+            .iter()
+            .map(|problem| {
+                let (line, column) = problem.position.unwrap();
+                (problem.path.clone(), line, column)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let problems = validate(
+            Path::new("pagoo.json"),
+            r#"{
+                "webhooks": [
+                    {
+                        "name": "deploy",
+                        "match": {"any": [{"match-headers": {"x-github-event": "push"}}]},
+                        "actions-to-execute": ["true"]
+                    }
+                ]
+            }"#,
+        );
+
+        assert_eq!(Vec::<Problem>::new(), problems);
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let problems = validate(Path::new("pagoo.json"), "{\n  \"webhooks\": [,]\n}");
+
+        assert_eq!(vec![("$".to_string(), 2, 16)], summary(&problems));
+        assert_eq!("expected value", problems[0].message);
+    }
+
+    #[test]
+    fn test_all_problems_are_reported() {
+        let problems = validate(
+            Path::new("pagoo.json"),
+            r#"{
+    "workers": 0,
+    "webhooks": [
+        {
+            "name": "deploy",
+            "matchers": [{"match-headers": {"x-event": {"regex": "(push"}}}],
+            "actions-to-execute": ["true"]
+        },
+        {
+            "name": "deploy",
+            "matchers": [],
+            "actions": []
+        },
+        {
+            "name": "build",
+            "matchers": [{}],
+            "actions-to-execute": ["some-inexistent-program-for-pagoo"]
+        },
+        {
+            "name": "never",
+            "match": {"any": []},
+            "actions-to-execute": ["true"]
+        },
+        {
+            "name": "invalid-expression",
+            "match": {"all": [{"not": {"match-json-path": [{"path": "$.ref", "regex": "["}]}}]},
+            "actions-to-execute": ["true"]
+        }
+    ]
+}"#,
+        );
+
+        assert_eq!(
+            vec![
+                ("$.workers".to_string(), 2, 16),
+                (
+                    "$.webhooks[0].matchers[0].match-headers.x-event".to_string(),
+                    6,
+                    56
+                ),
+                ("$.webhooks[1].name".to_string(), 10, 21),
+                ("$.webhooks[1]".to_string(), 9, 9),
+                ("$.webhooks[1]".to_string(), 9, 9),
+                ("$.webhooks[2].actions-to-execute".to_string(), 17, 35),
+                ("$.webhooks[2].matchers[0]".to_string(), 16, 26),
+                ("$.webhooks[3].match".to_string(), 21, 22),
+                (
+                    "$.webhooks[4].match.all[0].not.match-json-path[0]".to_string(),
+                    26,
+                    60
+                ),
+            ],
+            summary(&problems)
+        );
+        assert!(problems[1].message.contains("Invalid regex"));
+        assert!(problems[2]
+            .message
+            .contains("already used by $.webhooks[0]"));
+        assert!(problems[3].message.contains("non-empty \"actions\" list"));
+        assert!(problems[4].message.contains("non-empty \"matchers\" list"));
+        assert!(problems[5].message.contains("was not found in the PATH"));
+        assert!(problems[7].message.contains("unreachable"));
+        assert!(problems[8].message.contains("Invalid regex"));
+    }
+
+    #[test]
+    fn test_invalid_retry_multiplier() {
+        let problems = validate(
+            Path::new("pagoo.json"),
+            r#"{"webhooks": [
+                {"name": "deploy", "match": {"match-headers": {"x-event": "push"}}, "actions-to-execute": ["true"], "retry": {"max-attempts": 3, "multiplier": -1}}
+            ]}"#,
+        );
+
+        assert_eq!(
+            vec![("$.webhooks[0].retry.multiplier".to_string(), 2, 160)],
+            summary(&problems)
+        );
+        assert!(problems[0].message.contains("greater than or equal to 1"));
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let problems = validate(
+            Path::new("pagoo.json"),
+            r#"{"webhooks": [
+                {"name": "deploy", "match": {"match-headers": {"x-event": "push"}}, "actions-to-execute": ["true"]},
+                {"name": "deploy", "match": {"match-headers": {"x-event": "release"}}, "actions-to-execute": ["true"]}
+            ]}"#,
+        );
+
+        assert_eq!(
+            vec![("$.webhooks[1].name".to_string(), 3, 26)],
+            summary(&problems)
+        );
+        assert_eq!(
+            "Webhook name \"deploy\" is already used by $.webhooks[0].",
+            problems[0].message
+        );
+    }
+
+    #[test]
+    fn test_included_files() {
+        let dir = std::env::temp_dir().join(format!("pagoo-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("conf.d")).unwrap();
+        let included_file = dir.join("conf.d/deploy.json");
+        std::fs::write(
+            &included_file,
+            r#"{
+    "include": ["*.json"],
+    "webhooks": [
+        {"name": "deploy", "match": {"any": []}, "actions-to-execute": ["true"]}
+    ]
+}"#,
+        )
+        .unwrap();
+
+        let problems = validate(
+            &dir.join("pagoo.json"),
+            r#"{
+                "include": ["conf.d", "missing.json"],
+                "webhooks": [
+                    {"name": "deploy", "match": {"match-headers": {"x-event": "push"}}, "actions-to-execute": ["true"]}
+                ]
+            }"#,
+        );
+
+        let included_file = included_file.display().to_string();
+        assert_eq!(
+            vec![
+                ("$.include[1]".to_string(), 2, 39),
+                ("$.include".to_string(), 2, 16),
+                ("$.webhooks[0].name".to_string(), 4, 18),
+                ("$.webhooks[0].match".to_string(), 4, 37),
+            ],
+            summary(&problems)
+        );
+        assert!(problems[0].message.contains("was not found"));
+        assert!(problems[1..]
+            .iter()
+            .all(|problem| problem.file == included_file));
+        assert_eq!(
+            format!(
+                "Webhook name \"deploy\" is already used by $.webhooks[0] in {}.",
+                dir.join("pagoo.json").display()
+            ),
+            problems[2].message
+        );
+        assert!(problems[3].message.contains("unreachable"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_other_formats() {
+        let problems = validate(
+            Path::new("pagoo.json5"),
+            r#"{
+                // Comments are skipped when looking for the position of a value.
+                webhooks: [
+                    {name: 'deploy', match: {any: []}, "actions-to-execute": ["true"]},
+                ],
+            }"#,
+        );
+        assert_eq!(
+            vec![("$.webhooks[0].match".to_string(), 4, 45)],
+            summary(&problems)
+        );
+
+        let problems = validate(
+            Path::new("pagoo.yaml"),
+            "webhooks:\n  - name: deploy\n    match: {any: []}\n    actions-to-execute: [\"true\"]\n",
+        );
+        assert_eq!(1, problems.len());
+        assert_eq!("$.webhooks[0].match", problems[0].path);
+        assert_eq!(None, problems[0].position);
+    }
+
+    #[test]
+    fn test_can_match() {
+        let expression: MatcherExpression = serde_json::from_str(
+            r#"{"all": [{"match-headers": {"x-event": "push"}}, {"not": {"all": []}}]}"#,
+        )
+        .unwrap();
+        assert!(!can_match(&expression));
+
+        let expression: MatcherExpression = serde_json::from_str(
+            r#"{"any": [{"match-headers": {"x-event": "push"}}, {"any": []}]}"#,
+        )
+        .unwrap();
+        assert!(can_match(&expression));
+    }
+
+    #[test]
+    fn test_format_path() {
+        assert_eq!(
+            "$.webhooks[0][\"x.y\"]",
+            format_path(&[key("webhooks"), PathSegment::Index(0), key("x.y")])
+        );
+    }
+}
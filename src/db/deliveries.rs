@@ -0,0 +1,208 @@
+use crate::actions::request::IncomingRequest;
+use crate::actions::request::Job;
+use crate::db::jobs;
+use crate::logging;
+use rusqlite::named_params;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Delivery {
+    pub(crate) id: String,
+    pub(crate) received_at: String,
+    pub(crate) source_ip: Option<String>,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: String,
+    pub(crate) matched_webhooks: Vec<String>,
+    pub(crate) replay_of: Option<String>,
+}
+
+/// Headers that carry credentials: a signature of the body can be replayed,
+/// and the GitLab token is the webhook secret itself.
+const CREDENTIAL_HEADERS: [&str; 9] = [
+    "authorization",
+    "cookie",
+    "proxy-authorization",
+    "stripe-signature",
+    "x-gitea-signature",
+    "x-gitlab-token",
+    "x-hub-signature",
+    "x-hub-signature-256",
+    "x-signature",
+];
+
+impl Delivery {
+    /// The raw headers are stored for replays, but the credentials they contain
+    /// and the configured secrets are hidden when the delivery is displayed.
+    pub(crate) fn redacted(mut self) -> Self {
+        for (name, value) in self.headers.iter_mut() {
+            *value = match CREDENTIAL_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+                true => logging::REDACTED.to_string(),
+                false => logging::redact(value).into_owned(),
+            };
+        }
+
+        self
+    }
+}
+
+/// Stores an incoming request with the jobs of the webhooks it matched, all of them or none.
+pub(crate) fn insert_delivery(
+    conn: &mut Connection,
+    request: &IncomingRequest,
+    jobs: &[Job],
+) -> anyhow::Result<()> {
+    let matched_webhooks: Vec<&str> = jobs.iter().map(|job| job.webhook.name.as_str()).collect();
+
+    let transaction = conn.transaction()?;
+
+    transaction.execute(
+        "
+        INSERT INTO deliveries (
+            id,
+            received_at,
+            source_ip,
+            headers,
+            body,
+            matched_webhooks,
+            replay_of
+        ) VALUES (
+            :id,
+            datetime(),
+            :source_ip,
+            :headers,
+            :body,
+            :matched_webhooks,
+            :replay_of
+        )
+        ",
+        named_params! {
+            ":id": request.id,
+            ":source_ip": request.source_ip,
+            ":headers": serde_json::to_string(&request.headers)?,
+            ":body": request.body,
+            ":matched_webhooks": serde_json::to_string(&matched_webhooks)?,
+            ":replay_of": request.replay_of,
+        },
+    )?;
+
+    jobs::insert_jobs(&transaction, jobs)?;
+
+    transaction.commit()?;
+
+    Ok(())
+}
+
+pub(crate) fn get_delivery(conn: &Connection, id: &str) -> anyhow::Result<Option<Delivery>> {
+    let delivery = conn
+        .query_row(
+            "
+            SELECT id, received_at, source_ip, headers, body, matched_webhooks, replay_of
+            FROM deliveries
+            WHERE id = :id
+            ",
+            named_params! {":id": id},
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                ))
+            },
+        )
+        .optional()?;
+
+    let (id, received_at, source_ip, headers, body, matched_webhooks, replay_of) = match delivery {
+        Some(delivery) => delivery,
+        None => return Ok(None),
+    };
+
+    Ok(Some(Delivery {
+        id,
+        received_at,
+        source_ip,
+        headers: serde_json::from_str(&headers)?,
+        body,
+        matched_webhooks: serde_json::from_str(&matched_webhooks)?,
+        replay_of,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Webhook;
+    use crate::test_utils;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_redacted_headers() {
+        let delivery = Delivery {
+            id: "delivery-id".to_string(),
+            received_at: "2024-01-31 12:00:00".to_string(),
+            source_ip: None,
+            headers: vec![
+                ("x-gitlab-token".to_string(), "webhook-secret".to_string()),
+                ("X-Hub-Signature-256".to_string(), "sha256=abc".to_string()),
+                ("x-gitlab-event".to_string(), "Push Hook".to_string()),
+            ],
+            body: "{}".to_string(),
+            matched_webhooks: vec![],
+            replay_of: None,
+        };
+
+        assert_eq!(
+            vec![
+                ("x-gitlab-token".to_string(), "[REDACTED]".to_string()),
+                ("X-Hub-Signature-256".to_string(), "[REDACTED]".to_string()),
+                ("x-gitlab-event".to_string(), "Push Hook".to_string()),
+            ],
+            delivery.redacted().headers
+        );
+    }
+
+    #[test]
+    fn test_insert_delivery() {
+        let mut conn = test_utils::get_test_database_connection();
+        let request = Arc::new(IncomingRequest {
+            id: "delivery-id".to_string(),
+            body: "{}".to_string(),
+            headers: vec![("x-github-event".to_string(), "push".to_string())],
+            source_ip: Some("127.0.0.1".to_string()),
+            replay_of: None,
+        });
+        let webhook = Webhook {
+            name: "deploy".to_string(),
+            ..Default::default()
+        };
+        let jobs = vec![Job::new(Arc::clone(&request), webhook)];
+
+        insert_delivery(&mut conn, &request, &jobs).unwrap();
+
+        let (source_ip, headers, matched_webhooks): (String, String, String) = conn
+            .query_row(
+                "SELECT source_ip, headers, matched_webhooks FROM deliveries WHERE id = 'delivery-id'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!("127.0.0.1", source_ip);
+        assert_eq!(r#"[["x-github-event","push"]]"#, headers);
+        assert_eq!(r#"["deploy"]"#, matched_webhooks);
+
+        let delivery_id: String = conn
+            .query_row("SELECT delivery_id FROM jobs", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!("delivery-id", delivery_id);
+
+        let delivery = get_delivery(&conn, "delivery-id").unwrap().unwrap();
+        assert_eq!(request.headers, delivery.headers);
+        assert_eq!(vec!["deploy"], delivery.matched_webhooks);
+        assert!(get_delivery(&conn, "unknown").unwrap().is_none());
+    }
+}
@@ -0,0 +1,381 @@
+use crate::actions::executor::CommandStatus;
+use regex::Regex;
+use rusqlite::named_params;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension;
+use rusqlite::Row;
+use serde::Serialize;
+
+/// Status filter matching all the executions that did not succeed and were not cancelled.
+pub(crate) const FAILED_STATUS_FILTER: &str = "failed";
+
+/// Restricts the executions returned by "find_executions()". Empty fields do not filter anything.
+#[derive(Debug, Default)]
+pub(crate) struct ExecutionFilter {
+    pub(crate) webhook_name: Option<String>,
+    pub(crate) delivery_id: Option<String>,
+    /// A date as returned by "resolve_since()".
+    pub(crate) since: Option<String>,
+    pub(crate) statuses: Vec<String>,
+    /// Only executions stored after the one with this row id.
+    pub(crate) after_rowid: Option<i64>,
+    pub(crate) limit: Option<usize>,
+    pub(crate) offset: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ExecutionSummary {
+    #[serde(skip)]
+    pub(crate) rowid: i64,
+    pub(crate) id: String,
+    pub(crate) delivery_id: Option<String>,
+    pub(crate) job_id: Option<String>,
+    pub(crate) webhook_name: String,
+    pub(crate) status: String,
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) signal: Option<i32>,
+    pub(crate) started_at: String,
+    pub(crate) finished_at: String,
+    pub(crate) duration_ms: Option<i64>,
+    pub(crate) attempt: u32,
+    pub(crate) max_attempts: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ExecutionDetails {
+    #[serde(flatten)]
+    pub(crate) summary: ExecutionSummary,
+    pub(crate) argv: Vec<Vec<String>>,
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+    pub(crate) steps: Vec<ExecutionStep>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ExecutionStep {
+    pub(crate) name: String,
+    pub(crate) status: String,
+    /// Empty for skipped steps, and for steps logged before argv were stored.
+    pub(crate) argv: Option<Vec<String>>,
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) signal: Option<i32>,
+    pub(crate) duration_ms: Option<i64>,
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+}
+
+const SUMMARY_COLUMNS: &str = "
+    rowid,
+    id,
+    delivery_id,
+    job_id,
+    webhook_name,
+    status,
+    exit_code,
+    signal,
+    started_at,
+    finished_at,
+    duration_ms,
+    attempt,
+    max_attempts
+";
+
+/// Statuses to filter on, with "failed" standing for all the failing statuses.
+pub(crate) fn expand_status_filter(status: &str) -> Vec<String> {
+    if status == FAILED_STATUS_FILTER {
+        return [
+            CommandStatus::Failure,
+            CommandStatus::Signaled,
+            CommandStatus::SpawnError,
+            CommandStatus::TimedOut,
+        ]
+        .iter()
+        .map(|status| status.to_string())
+        .collect();
+    }
+
+    vec![status.to_string()]
+}
+
+/// Converts a duration relative to now ("30s", "15m", "2h", "7d") or a date
+/// ("2024-01-31", "2024-01-31 12:00:00") to a date comparable with the stored ones.
+pub(crate) fn resolve_since(conn: &Connection, since: &str) -> anyhow::Result<String> {
+    let relative = Regex::new(r"^(\d+)([smhd])$").unwrap();
+
+    let (date, modifier) = match relative.captures(since.trim()) {
+        Some(captures) => {
+            let unit = match &captures[2] {
+                "s" => "seconds",
+                "m" => "minutes",
+                "h" => "hours",
+                _ => "days",
+            };
+            ("now".to_string(), format!("-{} {}", &captures[1], unit))
+        }
+        None => (since.trim().to_string(), "+0 seconds".to_string()),
+    };
+
+    let resolved: Option<String> = conn.query_row(
+        "SELECT datetime(:date, :modifier)",
+        named_params! {":date": date, ":modifier": modifier},
+        |row| row.get(0),
+    )?;
+
+    resolved.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Invalid date \"{}\". Use a date like \"2024-01-31 12:00:00\" or a duration like \"30m\", \"2h\" or \"7d\".",
+            since
+        )
+    })
+}
+
+/// Executions matching the filter, the most recent first.
+pub(crate) fn find_executions(
+    conn: &Connection,
+    filter: &ExecutionFilter,
+) -> anyhow::Result<Vec<ExecutionSummary>> {
+    let statuses = if filter.statuses.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&filter.statuses)?)
+    };
+
+    let mut statement = conn.prepare(&format!(
+        "
+        SELECT {}
+        FROM executions
+        WHERE (:webhook_name IS NULL OR webhook_name = :webhook_name)
+        AND (:delivery_id IS NULL OR delivery_id = :delivery_id)
+        AND (:since IS NULL OR started_at >= :since)
+        AND (:statuses IS NULL OR status IN (SELECT value FROM json_each(:statuses)))
+        AND (:after_rowid IS NULL OR rowid > :after_rowid)
+        ORDER BY rowid DESC
+        LIMIT :limit OFFSET :offset
+        ",
+        SUMMARY_COLUMNS
+    ))?;
+
+    let rows = statement.query_map(
+        named_params! {
+            ":webhook_name": filter.webhook_name,
+            ":delivery_id": filter.delivery_id,
+            ":since": filter.since,
+            ":statuses": statuses,
+            ":after_rowid": filter.after_rowid,
+            // A negative limit means no limit for SQLite.
+            ":limit": filter.limit.map_or(-1, |limit| limit as i64),
+            ":offset": filter.offset as i64,
+        },
+        summary_from_row,
+    )?;
+
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+pub(crate) fn get_execution(
+    conn: &Connection,
+    id: &str,
+) -> anyhow::Result<Option<ExecutionDetails>> {
+    let execution = conn
+        .query_row(
+            &format!(
+                "SELECT {}, argv, stdout, stderr FROM executions WHERE id = :id",
+                SUMMARY_COLUMNS
+            ),
+            named_params! {":id": id},
+            |row| {
+                Ok((
+                    summary_from_row(row)?,
+                    row.get::<_, String>(13)?,
+                    row.get::<_, String>(14)?,
+                    row.get::<_, String>(15)?,
+                ))
+            },
+        )
+        .optional()?;
+
+    let (summary, argv, stdout, stderr) = match execution {
+        Some(execution) => execution,
+        None => return Ok(None),
+    };
+
+    let mut statement = conn.prepare(
+        "
+        SELECT
+            step_name,
+            command_status,
+            argv,
+            command_exit_code,
+            command_signal,
+            command_duration_ms,
+            command_stdout,
+            command_stderr
+        FROM execution_steps
+        WHERE execution_id = :id
+        ORDER BY position
+        ",
+    )?;
+
+    let rows = statement.query_map(named_params! {":id": id}, |row| {
+        Ok((
+            ExecutionStep {
+                name: row.get(0)?,
+                status: row.get(1)?,
+                argv: None,
+                exit_code: row.get(3)?,
+                signal: row.get(4)?,
+                duration_ms: row.get(5)?,
+                stdout: row.get(6)?,
+                stderr: row.get(7)?,
+            },
+            row.get::<_, Option<String>>(2)?,
+        ))
+    })?;
+
+    let mut steps = Vec::new();
+    for row in rows {
+        let (mut step, argv) = row?;
+        step.argv = argv.map(|argv| serde_json::from_str(&argv)).transpose()?;
+        steps.push(step);
+    }
+
+    Ok(Some(ExecutionDetails {
+        summary,
+        argv: serde_json::from_str(&argv)?,
+        stdout,
+        stderr,
+        steps,
+    }))
+}
+
+fn summary_from_row(row: &Row) -> rusqlite::Result<ExecutionSummary> {
+    Ok(ExecutionSummary {
+        rowid: row.get(0)?,
+        id: row.get(1)?,
+        delivery_id: row.get(2)?,
+        job_id: row.get(3)?,
+        webhook_name: row.get(4)?,
+        status: row.get(5)?,
+        exit_code: row.get(6)?,
+        signal: row.get(7)?,
+        started_at: row.get(8)?,
+        finished_at: row.get(9)?,
+        duration_ms: row.get(10)?,
+        attempt: row.get(11)?,
+        max_attempts: row.get(12)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    fn insert_execution(conn: &Connection, id: &str, webhook_name: &str, status: &str) {
+        conn.execute(
+            "
+            INSERT INTO executions (id, webhook_name, argv, started_at, finished_at, status, stdout, stderr)
+            VALUES (:id, :webhook_name, '[[\"make\"]]', '2024-01-31 12:00:00', '2024-01-31 12:00:01', :status, 'out', '')
+            ",
+            named_params! {":id": id, ":webhook_name": webhook_name, ":status": status},
+        )
+        .unwrap();
+    }
+
+    fn get_ids(executions: Vec<ExecutionSummary>) -> Vec<String> {
+        executions
+            .into_iter()
+            .map(|execution| execution.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_find_executions() {
+        let conn = test_utils::get_test_database_connection();
+        insert_execution(&conn, "1", "deploy", "success");
+        insert_execution(&conn, "2", "deploy", "timed_out");
+        insert_execution(&conn, "3", "build", "failure");
+        insert_execution(&conn, "4", "deploy", "cancelled");
+
+        let all = find_executions(&conn, &ExecutionFilter::default()).unwrap();
+        assert_eq!(vec!["4", "3", "2", "1"], get_ids(all));
+
+        let filter = ExecutionFilter {
+            statuses: expand_status_filter(FAILED_STATUS_FILTER),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec!["3", "2"],
+            get_ids(find_executions(&conn, &filter).unwrap())
+        );
+
+        let filter = ExecutionFilter {
+            webhook_name: Some("deploy".to_string()),
+            limit: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec!["4", "2"],
+            get_ids(find_executions(&conn, &filter).unwrap())
+        );
+
+        let filter = ExecutionFilter {
+            webhook_name: Some("deploy".to_string()),
+            limit: Some(2),
+            offset: 2,
+            ..Default::default()
+        };
+        assert_eq!(vec!["1"], get_ids(find_executions(&conn, &filter).unwrap()));
+
+        let filter = ExecutionFilter {
+            after_rowid: Some(2),
+            since: Some(resolve_since(&conn, "2024-01-31").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec!["4", "3"],
+            get_ids(find_executions(&conn, &filter).unwrap())
+        );
+
+        let filter = ExecutionFilter {
+            since: Some(resolve_since(&conn, "1h").unwrap()),
+            ..Default::default()
+        };
+        assert!(find_executions(&conn, &filter).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_since() {
+        let conn = test_utils::get_test_database_connection();
+
+        assert_eq!(
+            "2024-01-31 00:00:00",
+            resolve_since(&conn, "2024-01-31").unwrap()
+        );
+        assert!(resolve_since(&conn, "7d").is_ok());
+        assert!(resolve_since(&conn, "yesterday").is_err());
+    }
+
+    #[test]
+    fn test_get_execution() {
+        let conn = test_utils::get_test_database_connection();
+        insert_execution(&conn, "1", "deploy", "failure");
+        conn.execute(
+            "
+            INSERT INTO execution_steps (execution_id, position, step_name, command_status, command_stdout, command_stderr)
+            VALUES ('1', 0, 'build', 'skipped', '', '')
+            ",
+            [],
+        )
+        .unwrap();
+
+        let execution = get_execution(&conn, "1").unwrap().unwrap();
+        assert_eq!("deploy", execution.summary.webhook_name);
+        assert_eq!(vec![vec!["make".to_string()]], execution.argv);
+        assert_eq!("out", execution.stdout);
+        assert_eq!(1, execution.steps.len());
+        assert_eq!(None, execution.steps[0].argv);
+
+        assert!(get_execution(&conn, "unknown").unwrap().is_none());
+    }
+}
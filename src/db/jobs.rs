@@ -0,0 +1,426 @@
+use crate::actions::request::IncomingRequest;
+use crate::actions::request::Job;
+use crate::config;
+use crate::config::ConcurrencyPolicy;
+use crate::config::RetryPolicy;
+use crate::config::Secret;
+use crate::config::Step;
+use crate::config::Webhook;
+use crate::logging;
+use rusqlite::named_params;
+use rusqlite::Connection;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// The parts of a webhook needed to execute a job, stored with it so that
+/// the job is executed as it was matched, even after a restart.
+/// It is stored as a template: its secrets are stored as their placeholders,
+/// and resolved again when the job is loaded from the database.
+#[derive(Serialize, Deserialize)]
+struct StoredWebhook {
+    name: String,
+    steps: Vec<Step>,
+    pipe_body_to_stdin: bool,
+    concurrency: ConcurrencyPolicy,
+    #[serde(default)]
+    retry: Option<RetryPolicy>,
+    #[serde(default)]
+    source_file: Option<String>,
+    /// Jobs stored before secrets were replaced with their placeholders are not templates.
+    #[serde(default)]
+    template: bool,
+}
+
+impl Display for JobState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobState::Queued => write!(f, "queued"),
+            JobState::Running => write!(f, "running"),
+            JobState::Succeeded => write!(f, "succeeded"),
+            JobState::Failed => write!(f, "failed"),
+            JobState::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl From<&Webhook> for StoredWebhook {
+    fn from(webhook: &Webhook) -> Self {
+        Self {
+            name: webhook.name.clone(),
+            steps: webhook.steps().into_owned(),
+            pipe_body_to_stdin: webhook.pipe_body_to_stdin,
+            concurrency: webhook.concurrency,
+            retry: webhook.retry.clone(),
+            source_file: webhook.source_file.clone(),
+            template: true,
+        }
+    }
+}
+
+impl From<StoredWebhook> for Webhook {
+    fn from(stored: StoredWebhook) -> Self {
+        Self {
+            name: stored.name,
+            actions: stored.steps,
+            pipe_body_to_stdin: stored.pipe_body_to_stdin,
+            concurrency: stored.concurrency,
+            retry: stored.retry,
+            source_file: stored.source_file,
+            ..Default::default()
+        }
+    }
+}
+
+/// Stores the jobs as "queued", with the delivery they belong to.
+/// See "deliveries::insert_delivery()".
+pub(crate) fn insert_jobs(conn: &Connection, jobs: &[Job]) -> anyhow::Result<()> {
+    let secrets = logging::secrets();
+
+    for job in jobs {
+        conn.execute(
+            "
+            INSERT INTO jobs (
+                id,
+                delivery_id,
+                webhook_name,
+                webhook,
+                state,
+                created_at
+            ) VALUES (
+                :id,
+                :delivery_id,
+                :webhook_name,
+                :webhook,
+                :state,
+                datetime()
+            )
+            ",
+            named_params! {
+                ":id": job.id,
+                ":delivery_id": job.request.id,
+                ":webhook_name": job.webhook.name,
+                ":webhook": store_webhook(&job.webhook, &secrets)?,
+                ":state": JobState::Queued.to_string(),
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+fn store_webhook(webhook: &Webhook, secrets: &[Secret]) -> anyhow::Result<String> {
+    let mut stored = serde_json::to_value(StoredWebhook::from(webhook))?;
+    config::to_template(&mut stored, secrets);
+
+    Ok(serde_json::to_string(&stored)?)
+}
+
+fn restore_webhook(stored: &str) -> anyhow::Result<Webhook> {
+    let mut stored: serde_json::Value = serde_json::from_str(stored)?;
+    if stored["template"] == true {
+        config::from_template(&mut stored)?;
+    }
+
+    Ok(serde_json::from_value::<StoredWebhook>(stored)?.into())
+}
+
+/// Marks a queued job as running.
+/// Returns false if the job is not queued anymore, in which case it must not be executed.
+pub(crate) fn claim_job(conn: &Connection, job_id: &str) -> anyhow::Result<bool> {
+    let updated_rows = conn.execute(
+        "
+        UPDATE jobs
+        SET state = :running, started_at = datetime()
+        WHERE id = :id AND state = :queued
+        ",
+        named_params! {
+            ":id": job_id,
+            ":running": JobState::Running.to_string(),
+            ":queued": JobState::Queued.to_string(),
+        },
+    )?;
+
+    Ok(updated_rows == 1)
+}
+
+pub(crate) fn is_queued(conn: &Connection, job_id: &str) -> anyhow::Result<bool> {
+    let queued_jobs: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM jobs WHERE id = :id AND state = :queued",
+        named_params! {
+            ":id": job_id,
+            ":queued": JobState::Queued.to_string(),
+        },
+        |row| row.get(0),
+    )?;
+
+    Ok(queued_jobs == 1)
+}
+
+pub(crate) fn finish_job(conn: &Connection, job_id: &str, state: JobState) -> anyhow::Result<()> {
+    conn.execute(
+        "
+        UPDATE jobs
+        SET state = :state, finished_at = datetime()
+        WHERE id = :id
+        ",
+        named_params! {
+            ":id": job_id,
+            ":state": state.to_string(),
+        },
+    )?;
+
+    Ok(())
+}
+
+/// The webhook name and state of the jobs of a delivery.
+pub(crate) fn get_delivery_job_states(
+    conn: &Connection,
+    delivery_id: &str,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut statement = conn.prepare(
+        "
+        SELECT webhook_name, state
+        FROM jobs
+        WHERE delivery_id = :delivery_id
+        ORDER BY rowid
+        ",
+    )?;
+
+    let rows = statement.query_map(named_params! {":delivery_id": delivery_id}, |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Jobs that were running when the server stopped are queued again.
+/// Returns the number of jobs that were queued again.
+pub(crate) fn requeue_interrupted_jobs(conn: &Connection) -> anyhow::Result<usize> {
+    let updated_rows = conn.execute(
+        "
+        UPDATE jobs
+        SET state = :queued, started_at = NULL
+        WHERE state = :running
+        ",
+        named_params! {
+            ":queued": JobState::Queued.to_string(),
+            ":running": JobState::Running.to_string(),
+        },
+    )?;
+
+    Ok(updated_rows)
+}
+
+/// Queued jobs, in the order they were received.
+/// Jobs whose secrets cannot be resolved anymore are marked as failed.
+pub(crate) fn get_queued_jobs(conn: &Connection) -> anyhow::Result<Vec<Job>> {
+    let mut statement = conn.prepare(
+        "
+        SELECT
+            jobs.id,
+            deliveries.id,
+            deliveries.body,
+            deliveries.headers,
+            deliveries.source_ip,
+            deliveries.replay_of,
+            jobs.webhook
+        FROM jobs
+        INNER JOIN deliveries ON deliveries.id = jobs.delivery_id
+        WHERE jobs.state = :queued
+        ORDER BY jobs.rowid
+        ",
+    )?;
+
+    let rows = statement.query_map(
+        named_params! {":queued": JobState::Queued.to_string()},
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, String>(6)?,
+            ))
+        },
+    )?;
+
+    let mut requests: HashMap<String, Arc<IncomingRequest>> = HashMap::new();
+    let mut jobs = Vec::new();
+    let mut failed_jobs = Vec::new();
+
+    for row in rows {
+        let (id, delivery_id, body, headers, source_ip, replay_of, webhook) = row?;
+
+        let request = match requests.get(&delivery_id) {
+            Some(request) => Arc::clone(request),
+            None => {
+                let request = Arc::new(IncomingRequest {
+                    id: delivery_id.clone(),
+                    body,
+                    headers: serde_json::from_str(&headers)?,
+                    source_ip,
+                    replay_of,
+                });
+                requests.insert(delivery_id, Arc::clone(&request));
+                request
+            }
+        };
+
+        let webhook = match restore_webhook(&webhook) {
+            Ok(webhook) => webhook,
+            Err(err) => {
+                error!("Could not restore job \"{}\": {}", id, err);
+                failed_jobs.push(id);
+                continue;
+            }
+        };
+
+        let mut job = Job::new(request, webhook);
+        job.id = id;
+        jobs.push(job);
+    }
+
+    for job_id in failed_jobs {
+        finish_job(conn, &job_id, JobState::Failed)?;
+    }
+
+    Ok(jobs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::deliveries::insert_delivery;
+    use crate::test_utils;
+
+    fn get_job_state(conn: &Connection, job_id: &str) -> String {
+        conn.query_row(
+            "SELECT state FROM jobs WHERE id = :id",
+            named_params! {":id": job_id},
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn get_jobs() -> Vec<Job> {
+        let request = Arc::new(IncomingRequest {
+            id: "request-id".to_string(),
+            body: r#"{"ref": "refs/heads/main"}"#.to_string(),
+            headers: vec![("x-github-event".to_string(), "push".to_string())],
+            source_ip: None,
+            replay_of: None,
+        });
+
+        ["first", "second"]
+            .into_iter()
+            .map(|name| {
+                let webhook = Webhook {
+                    name: name.to_string(),
+                    actions_to_execute: Some(
+                        serde_json::from_str(r#"["echo", "{{ body.ref }}"]"#).unwrap(),
+                    ),
+                    concurrency: ConcurrencyPolicy::CancelPrevious,
+                    ..Default::default()
+                };
+                Job::new(Arc::clone(&request), webhook)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_queued_jobs_are_restored() {
+        let mut conn = test_utils::get_test_database_connection();
+        let jobs = get_jobs();
+
+        insert_delivery(&mut conn, &jobs[0].request, &jobs).unwrap();
+
+        let queued_jobs = get_queued_jobs(&conn).unwrap();
+        assert_eq!(2, queued_jobs.len());
+        assert_eq!(jobs[0].id, queued_jobs[0].id);
+        assert_eq!("first", queued_jobs[0].webhook.name);
+        assert_eq!("second", queued_jobs[1].webhook.name);
+        assert_eq!(jobs[0].webhook.steps(), queued_jobs[0].webhook.steps());
+        assert_eq!(
+            ConcurrencyPolicy::CancelPrevious,
+            queued_jobs[0].webhook.concurrency
+        );
+        assert_eq!(jobs[0].request.headers, queued_jobs[0].request.headers);
+        assert!(Arc::ptr_eq(
+            &queued_jobs[0].request,
+            &queued_jobs[1].request
+        ));
+    }
+
+    #[test]
+    fn test_secrets_are_not_stored() {
+        std::env::set_var("PAGOO_TEST_JOB_TOKEN", "job-deploy-token");
+        let webhook = Webhook {
+            name: "deploy".to_string(),
+            actions_to_execute: Some(
+                serde_json::from_str(
+                    r#"{"command": "echo ${HOME}", "env": {"TOKEN": "job-deploy-token"}}"#,
+                )
+                .unwrap(),
+            ),
+            ..Default::default()
+        };
+        let secrets = vec![Secret {
+            value: "job-deploy-token".to_string(),
+            placeholder: "${PAGOO_TEST_JOB_TOKEN}".to_string(),
+        }];
+
+        let stored = store_webhook(&webhook, &secrets).unwrap();
+
+        assert!(!stored.contains("job-deploy-token"));
+        assert!(stored.contains("$${HOME}"));
+        assert_eq!(webhook.steps(), restore_webhook(&stored).unwrap().steps());
+
+        std::env::remove_var("PAGOO_TEST_JOB_TOKEN");
+        assert!(restore_webhook(&stored).is_err());
+    }
+
+    #[test]
+    fn test_job_is_claimed_once() {
+        let mut conn = test_utils::get_test_database_connection();
+        let jobs = get_jobs();
+        insert_delivery(&mut conn, &jobs[0].request, &jobs).unwrap();
+
+        assert!(claim_job(&conn, &jobs[0].id).unwrap());
+        assert!(!claim_job(&conn, &jobs[0].id).unwrap());
+        assert_eq!("running", get_job_state(&conn, &jobs[0].id));
+
+        finish_job(&conn, &jobs[0].id, JobState::Succeeded).unwrap();
+        assert_eq!("succeeded", get_job_state(&conn, &jobs[0].id));
+
+        let queued_jobs = get_queued_jobs(&conn).unwrap();
+        assert_eq!(1, queued_jobs.len());
+        assert_eq!(jobs[1].id, queued_jobs[0].id);
+    }
+
+    #[test]
+    fn test_interrupted_jobs_are_queued_again() {
+        let mut conn = test_utils::get_test_database_connection();
+        let jobs = get_jobs();
+        insert_delivery(&mut conn, &jobs[0].request, &jobs).unwrap();
+        claim_job(&conn, &jobs[0].id).unwrap();
+
+        assert_eq!(1, requeue_interrupted_jobs(&conn).unwrap());
+
+        assert_eq!("queued", get_job_state(&conn, &jobs[0].id));
+        assert_eq!(2, get_queued_jobs(&conn).unwrap().len());
+    }
+}
@@ -0,0 +1,5 @@
+CREATE TABLE logs_rejected_requests (
+    rejection_date TEXT NOT NULL,
+    webhook_name TEXT NOT NULL,
+    rejection_reason TEXT NOT NULL
+);
@@ -0,0 +1,35 @@
+CREATE TABLE logs_webhooks_new (
+    execution_date TEXT NOT NULL,
+    webhook_name TEXT NOT NULL,
+    executed_command TEXT NOT NULL,
+    command_status TEXT NOT NULL,
+    command_exit_code INTEGER,
+    command_duration_ms INTEGER,
+    command_stdout TEXT NOT NULL,
+    command_stderr TEXT NOT NULL
+);
+
+INSERT INTO logs_webhooks_new (
+    execution_date,
+    webhook_name,
+    executed_command,
+    command_status,
+    command_exit_code,
+    command_duration_ms,
+    command_stdout,
+    command_stderr
+)
+SELECT
+    execution_date,
+    webhook_name,
+    executed_command,
+    CASE WHEN command_exit_code = 0 THEN 'success' ELSE 'failure' END,
+    command_exit_code,
+    NULL,
+    command_stdout,
+    command_stderr
+FROM logs_webhooks;
+
+DROP TABLE logs_webhooks;
+
+ALTER TABLE logs_webhooks_new RENAME TO logs_webhooks;
@@ -0,0 +1,14 @@
+CREATE TABLE jobs (
+    id TEXT PRIMARY KEY NOT NULL,
+    request_id TEXT NOT NULL,
+    request_body TEXT NOT NULL,
+    request_headers TEXT NOT NULL,
+    webhook_name TEXT NOT NULL,
+    webhook TEXT NOT NULL,
+    state TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    started_at TEXT,
+    finished_at TEXT
+);
+
+CREATE INDEX jobs_state ON jobs (state);
@@ -0,0 +1,5 @@
+ALTER TABLE logs_webhooks ADD COLUMN job_id TEXT;
+ALTER TABLE logs_webhooks ADD COLUMN attempt INTEGER;
+ALTER TABLE logs_webhooks ADD COLUMN max_attempts INTEGER;
+
+CREATE INDEX logs_webhooks_job_id ON logs_webhooks (job_id);
@@ -0,0 +1,15 @@
+ALTER TABLE logs_webhooks ADD COLUMN execution_id TEXT;
+
+CREATE TABLE logs_steps (
+    execution_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    step_name TEXT NOT NULL,
+    executed_command TEXT,
+    command_status TEXT NOT NULL,
+    command_exit_code INTEGER,
+    command_duration_ms INTEGER,
+    command_stdout TEXT NOT NULL,
+    command_stderr TEXT NOT NULL
+);
+
+CREATE INDEX logs_steps_execution_id ON logs_steps (execution_id);
@@ -0,0 +1,3 @@
+ALTER TABLE logs_webhooks ADD COLUMN command_signal INTEGER;
+
+ALTER TABLE logs_steps ADD COLUMN command_signal INTEGER;
@@ -0,0 +1,98 @@
+CREATE TABLE deliveries (
+    id TEXT PRIMARY KEY NOT NULL,
+    received_at TEXT NOT NULL,
+    source_ip TEXT,
+    headers TEXT NOT NULL,
+    body TEXT NOT NULL,
+    matched_webhooks TEXT NOT NULL
+);
+
+CREATE INDEX deliveries_received_at ON deliveries (received_at);
+
+INSERT INTO deliveries (id, received_at, source_ip, headers, body, matched_webhooks)
+SELECT
+    request_id,
+    MIN(created_at),
+    NULL,
+    request_headers,
+    request_body,
+    json_group_array(webhook_name)
+FROM jobs
+GROUP BY request_id;
+
+ALTER TABLE jobs RENAME COLUMN request_id TO delivery_id;
+ALTER TABLE jobs DROP COLUMN request_body;
+ALTER TABLE jobs DROP COLUMN request_headers;
+
+CREATE INDEX jobs_delivery_id ON jobs (delivery_id);
+
+CREATE TABLE executions (
+    id TEXT PRIMARY KEY NOT NULL,
+    delivery_id TEXT REFERENCES deliveries (id),
+    job_id TEXT REFERENCES jobs (id),
+    webhook_name TEXT NOT NULL,
+    argv TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    finished_at TEXT NOT NULL,
+    duration_ms INTEGER,
+    status TEXT NOT NULL,
+    exit_code INTEGER,
+    signal INTEGER,
+    stdout TEXT NOT NULL,
+    stderr TEXT NOT NULL,
+    attempt INTEGER NOT NULL DEFAULT 1,
+    max_attempts INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX executions_delivery_id ON executions (delivery_id);
+CREATE INDEX executions_webhook_name ON executions (webhook_name, started_at);
+CREATE INDEX executions_started_at ON executions (started_at);
+CREATE INDEX executions_status ON executions (status, started_at);
+
+-- Executions logged before this migration only have their command as a single string,
+-- joined with spaces. It cannot be split reliably, so this is lossy: the whole command
+-- is stored as the only argument of a single step.
+INSERT INTO executions (
+    id,
+    delivery_id,
+    job_id,
+    webhook_name,
+    argv,
+    started_at,
+    finished_at,
+    duration_ms,
+    status,
+    exit_code,
+    signal,
+    stdout,
+    stderr,
+    attempt,
+    max_attempts
+)
+SELECT
+    COALESCE(logs.execution_id, lower(hex(randomblob(16)))),
+    jobs.delivery_id,
+    jobs.id,
+    logs.webhook_name,
+    json_array(json_array(logs.executed_command)),
+    logs.execution_date,
+    logs.execution_date,
+    logs.command_duration_ms,
+    logs.command_status,
+    logs.command_exit_code,
+    logs.command_signal,
+    logs.command_stdout,
+    logs.command_stderr,
+    COALESCE(logs.attempt, 1),
+    COALESCE(logs.max_attempts, 1)
+FROM logs_webhooks AS logs
+LEFT JOIN jobs ON jobs.id = logs.job_id
+ORDER BY logs.rowid;
+
+DROP TABLE logs_webhooks;
+
+ALTER TABLE logs_steps RENAME TO execution_steps;
+
+DROP INDEX logs_steps_execution_id;
+CREATE INDEX execution_steps_execution_id ON execution_steps (execution_id);
+ALTER TABLE execution_steps ADD COLUMN argv TEXT;
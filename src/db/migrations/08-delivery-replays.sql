@@ -0,0 +1 @@
+ALTER TABLE deliveries ADD COLUMN replay_of TEXT REFERENCES deliveries (id);
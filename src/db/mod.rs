@@ -1,10 +1,24 @@
+pub(crate) mod deliveries;
+pub(crate) mod executions;
+pub(crate) mod jobs;
+
 use anyhow::Context;
 use rusqlite::Connection;
 use rusqlite::OpenFlags;
 use rusqlite_migration::Migrations;
 use rusqlite_migration::M;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
 use std::path::PathBuf;
 
+/// An exclusive lock on a file next to the database, held by the process executing its
+/// queued jobs, which is usually the server. It is released when dropped.
+pub(crate) struct WorkersLock {
+    _file: File,
+}
+
 pub(crate) fn get_database_connection(
     configured_path: Option<String>,
 ) -> anyhow::Result<Connection> {
@@ -19,6 +33,38 @@ pub(crate) fn get_database_connection(
     Ok(conn)
 }
 
+/// Returns None when another process already holds the lock.
+pub(crate) fn lock_workers(configured_path: Option<String>) -> anyhow::Result<Option<WorkersLock>> {
+    let mut lock_path = get_database_path(configured_path).into_os_string();
+    lock_path.push(".lock");
+
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .mode(0o600)
+        .open(&lock_path)?;
+
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(Some(WorkersLock { _file: file }));
+    }
+
+    let err = std::io::Error::last_os_error();
+    match err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        true => Ok(None),
+        false => Err(anyhow::anyhow!(
+            "Could not lock \"{}\": {}",
+            lock_path.to_string_lossy(),
+            err
+        )),
+    }
+}
+
+/// Whether a server executes the jobs queued in the database.
+pub(crate) fn is_server_running(configured_path: Option<String>) -> anyhow::Result<bool> {
+    Ok(lock_workers(configured_path)?.is_none())
+}
+
 fn get_database_path(configured_path: Option<String>) -> PathBuf {
     let path = if let Some(file) = configured_path {
         file
@@ -47,5 +93,124 @@ fn get_database_flags() -> OpenFlags {
 }
 
 fn get_migrations() -> Migrations<'static> {
-    Migrations::new(vec![M::up(include_str!("./migrations/00-schema.sql"))])
+    Migrations::new(vec![
+        M::up(include_str!("./migrations/00-schema.sql")),
+        M::up(include_str!("./migrations/01-rejected-requests.sql")),
+        M::up(include_str!("./migrations/02-command-status.sql")),
+        M::up(include_str!("./migrations/03-jobs.sql")),
+        M::up(include_str!("./migrations/04-attempts.sql")),
+        M::up(include_str!("./migrations/05-steps.sql")),
+        M::up(include_str!("./migrations/06-command-signal.sql")),
+        M::up(include_str!("./migrations/07-deliveries-executions.sql")),
+        M::up(include_str!("./migrations/08-delivery-replays.sql")),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_logs_are_migrated() {
+        let database_file = std::env::temp_dir()
+            .join(format!("pagoo-{}.db3", uuid::Uuid::new_v4()))
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        // A database of the last version before deliveries, at migration 06.
+        let mut conn = Connection::open(&database_file).unwrap();
+        get_migrations().to_version(&mut conn, 7).unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO jobs (id, request_id, request_body, request_headers, webhook_name, webhook, state, created_at)
+            VALUES
+                ('job-1', 'request-1', '{"ref": "main"}', '[["x-github-event", "push"]]', 'deploy', '{}', 'succeeded', '2024-01-01 10:00:00'),
+                ('job-2', 'request-1', '{"ref": "main"}', '[["x-github-event", "push"]]', 'build', '{}', 'failed', '2024-01-01 10:00:01');
+            INSERT INTO logs_webhooks (execution_date, webhook_name, executed_command, command_status, command_exit_code, command_duration_ms, command_stdout, command_stderr, job_id, attempt, max_attempts, execution_id)
+            VALUES
+                ('2024-01-01 10:00:02', 'deploy', 'echo hi', 'success', 0, 12, 'hi', '', 'job-1', 1, 1, 'execution-1'),
+                ('2023-12-31 09:00:00', 'old', 'make build', 'failure', 2, NULL, '', 'error', NULL, NULL, NULL, NULL);
+            "#,
+        )
+        .unwrap();
+        drop(conn);
+
+        let conn = get_database_connection(Some(database_file.clone())).unwrap();
+
+        let delivery: (String, String, String, String) = conn
+            .query_row(
+                "SELECT id, received_at, headers, matched_webhooks FROM deliveries",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (
+                "request-1".to_string(),
+                "2024-01-01 10:00:00".to_string(),
+                r#"[["x-github-event", "push"]]"#.to_string(),
+                r#"["deploy","build"]"#.to_string(),
+            ),
+            delivery
+        );
+
+        let mut statement = conn
+            .prepare(
+                "
+                SELECT json_array(id, delivery_id, job_id, webhook_name, argv, status, exit_code, attempt)
+                FROM executions
+                ORDER BY started_at
+                ",
+            )
+            .unwrap();
+        let executions: Vec<serde_json::Value> = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .unwrap()
+            .map(|execution| serde_json::from_str(&execution.unwrap()).unwrap())
+            .collect();
+
+        assert_eq!(2, executions.len());
+        // Its id is generated. The legacy command cannot be split, it is the only argument.
+        let legacy_execution = &executions[0].as_array().unwrap()[1..];
+        assert_eq!(
+            serde_json::json!([null, null, "old", r#"[["make build"]]"#, "failure", 2, 1])
+                .as_array()
+                .unwrap(),
+            legacy_execution
+        );
+        assert_eq!(
+            serde_json::json!([
+                "execution-1",
+                "request-1",
+                "job-1",
+                "deploy",
+                r#"[["echo hi"]]"#,
+                "success",
+                0,
+                1
+            ]),
+            executions[1]
+        );
+
+        std::fs::remove_file(database_file).unwrap();
+    }
+
+    #[test]
+    fn test_lock_workers() {
+        let database_file = std::env::temp_dir()
+            .join(format!("pagoo-{}.db3", uuid::Uuid::new_v4()))
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let lock = lock_workers(Some(database_file.clone())).unwrap();
+        assert!(lock.is_some());
+        assert!(is_server_running(Some(database_file.clone())).unwrap());
+
+        drop(lock);
+        assert!(!is_server_running(Some(database_file.clone())).unwrap());
+
+        std::fs::remove_file(format!("{}.lock", database_file)).unwrap();
+    }
 }
@@ -0,0 +1,472 @@
+use crate::actions::replay;
+use crate::actions::request::Job;
+use crate::config::Config;
+use crate::config::SharedConfig;
+use crate::db::deliveries;
+use crate::db::executions;
+use crate::db::executions::ExecutionFilter;
+use actix_web::http::header;
+use actix_web::web;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use rusqlite::Connection;
+use serde::Deserialize;
+use std::sync::Mutex;
+use subtle::ConstantTimeEq;
+use tokio::sync::mpsc;
+
+pub(crate) const API_PREFIX: &str = "/api";
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ExecutionsQuery {
+    webhook: Option<String>,
+    delivery: Option<String>,
+    /// A date or a duration, see "executions::resolve_since()".
+    since: Option<String>,
+    /// Comma-separated statuses, "failed" matching all the failing ones.
+    status: Option<String>,
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+}
+
+pub(crate) async fn list_executions(
+    request: HttpRequest,
+    query: web::Query<ExecutionsQuery>,
+    config: web::Data<SharedConfig>,
+    database: web::Data<Mutex<Connection>>,
+) -> HttpResponse {
+    let config = config.get();
+
+    if let Some(response) = reject_unauthorized(&request, &config) {
+        return response;
+    }
+
+    let conn = database
+        .lock()
+        .expect("Could not retrieve database connection.");
+
+    let since = match query.since.as_ref() {
+        Some(since) => match executions::resolve_since(&conn, since) {
+            Ok(since) => Some(since),
+            Err(err) => return json_error(HttpResponse::BadRequest(), &err.to_string()),
+        },
+        None => None,
+    };
+    let statuses = query
+        .status
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|status| !status.is_empty())
+        .flat_map(executions::expand_status_filter)
+        .collect();
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    // One more execution is fetched to know if there is a next page.
+    let filter = ExecutionFilter {
+        webhook_name: query.webhook.clone(),
+        delivery_id: query.delivery.clone(),
+        since,
+        statuses,
+        limit: Some(limit + 1),
+        offset: query.offset,
+        ..Default::default()
+    };
+
+    let mut found = match executions::find_executions(&conn, &filter) {
+        Ok(found) => found,
+        Err(err) => return internal_error(err),
+    };
+
+    let next_offset = if found.len() > limit {
+        found.truncate(limit);
+        Some(query.offset + limit)
+    } else {
+        None
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "executions": found,
+        "limit": limit,
+        "offset": query.offset,
+        "next_offset": next_offset,
+    }))
+}
+
+pub(crate) async fn get_execution(
+    request: HttpRequest,
+    id: web::Path<String>,
+    config: web::Data<SharedConfig>,
+    database: web::Data<Mutex<Connection>>,
+) -> HttpResponse {
+    let config = config.get();
+
+    if let Some(response) = reject_unauthorized(&request, &config) {
+        return response;
+    }
+
+    let conn = database
+        .lock()
+        .expect("Could not retrieve database connection.");
+
+    match executions::get_execution(&conn, &id) {
+        Ok(Some(execution)) => HttpResponse::Ok().json(execution),
+        Ok(None) => json_error(
+            HttpResponse::NotFound(),
+            &format!("Execution \"{}\" does not exist.", id),
+        ),
+        Err(err) => internal_error(err),
+    }
+}
+
+pub(crate) async fn get_delivery(
+    request: HttpRequest,
+    id: web::Path<String>,
+    config: web::Data<SharedConfig>,
+    database: web::Data<Mutex<Connection>>,
+) -> HttpResponse {
+    let config = config.get();
+
+    if let Some(response) = reject_unauthorized(&request, &config) {
+        return response;
+    }
+
+    let conn = database
+        .lock()
+        .expect("Could not retrieve database connection.");
+
+    let delivery = match deliveries::get_delivery(&conn, &id) {
+        Ok(Some(delivery)) => delivery.redacted(),
+        Ok(None) => {
+            return json_error(
+                HttpResponse::NotFound(),
+                &format!("Delivery \"{}\" does not exist.", id),
+            )
+        }
+        Err(err) => return internal_error(err),
+    };
+
+    let filter = ExecutionFilter {
+        delivery_id: Some(delivery.id.clone()),
+        ..Default::default()
+    };
+
+    match executions::find_executions(&conn, &filter) {
+        Ok(found) => HttpResponse::Ok().json(serde_json::json!({
+            "delivery": delivery,
+            "executions": found,
+        })),
+        Err(err) => internal_error(err),
+    }
+}
+
+/// Matches the delivery against the current configuration, and queues the actions to execute.
+pub(crate) async fn replay_delivery(
+    request: HttpRequest,
+    id: web::Path<String>,
+    config: web::Data<SharedConfig>,
+    queue_sender: web::Data<mpsc::Sender<Vec<Job>>>,
+    database: web::Data<Mutex<Connection>>,
+) -> HttpResponse {
+    let config = config.get();
+
+    if let Some(response) = reject_unauthorized(&request, &config) {
+        return response;
+    }
+
+    let replay = {
+        let mut conn = database
+            .lock()
+            .expect("Could not retrieve database connection.");
+
+        replay::replay_delivery(&config, &mut conn, &id)
+    };
+
+    let replay = match replay {
+        Ok(Some(replay)) => replay,
+        Ok(None) => {
+            return json_error(
+                HttpResponse::NotFound(),
+                &format!("Delivery \"{}\" does not exist.", id),
+            )
+        }
+        Err(err) => {
+            error!("Could not replay delivery \"{}\": {:?}", id, err);
+            return json_error(
+                HttpResponse::InternalServerError(),
+                "Could not replay the delivery.",
+            );
+        }
+    };
+
+    let matching_webhooks: Vec<String> = replay
+        .jobs
+        .iter()
+        .map(|job| job.webhook.name.clone())
+        .collect();
+
+    if !replay.jobs.is_empty() {
+        if let Err(err) = queue_sender.send(replay.jobs).await {
+            error!("Could not send message to queue: {:?}", err);
+            return json_error(
+                HttpResponse::InternalServerError(),
+                "Could not send message to queue.",
+            );
+        }
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "delivery_id": replay.request.id,
+        "replay_of": replay.request.replay_of,
+        "matching_webhooks": matching_webhooks,
+    }))
+}
+
+/// Checks the "Authorization: Bearer <token>" header against the "api-token" option,
+/// and returns the response to send if the request is not allowed.
+fn reject_unauthorized(request: &HttpRequest, config: &Config) -> Option<HttpResponse> {
+    let expected_token = match &config.api_token {
+        Some(token) => token,
+        None => {
+            return Some(json_error(
+                HttpResponse::NotFound(),
+                "The API is disabled, set the \"api-token\" option to enable it.",
+            ))
+        }
+    };
+
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    match token {
+        Some(token) if bool::from(token.as_bytes().ct_eq(expected_token.as_bytes())) => None,
+        _ => {
+            warn!(
+                "Rejected API request to \"{}\": invalid token.",
+                request.path()
+            );
+            Some(json_error(
+                HttpResponse::Unauthorized()
+                    .append_header((header::WWW_AUTHENTICATE, "Bearer"))
+                    .take(),
+                "Invalid or missing bearer token.",
+            ))
+        }
+    }
+}
+
+fn json_error(mut response: actix_web::HttpResponseBuilder, message: &str) -> HttpResponse {
+    response.json(serde_json::json!({ "error": message }))
+}
+
+fn internal_error(err: anyhow::Error) -> HttpResponse {
+    error!("Could not read the logs: {:?}", err);
+
+    json_error(
+        HttpResponse::InternalServerError(),
+        "Could not read the logs.",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::request::IncomingRequest;
+    use crate::config::Webhook;
+    use crate::test_utils;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use actix_web::test::{call_service, init_service, read_body_json};
+    use actix_web::App;
+    use rusqlite::named_params;
+    use std::sync::Arc;
+
+    fn get_database() -> web::Data<Mutex<Connection>> {
+        let mut conn = test_utils::get_test_database_connection();
+
+        let request = Arc::new(IncomingRequest {
+            id: "delivery-id".to_string(),
+            body: r#"{"ref": "refs/heads/main"}"#.to_string(),
+            headers: vec![
+                ("x-gitlab-event".to_string(), "Push Hook".to_string()),
+                (
+                    "x-gitlab-token".to_string(),
+                    "gitlab-webhook-secret".to_string(),
+                ),
+            ],
+            ..Default::default()
+        });
+        let webhook = Webhook {
+            name: "deploy".to_string(),
+            ..Default::default()
+        };
+        let jobs = vec![Job::new(Arc::clone(&request), webhook)];
+        deliveries::insert_delivery(&mut conn, &request, &jobs).unwrap();
+
+        for (id, status) in [("1", "success"), ("2", "failure"), ("3", "timed_out")] {
+            conn.execute(
+                "
+                INSERT INTO executions (id, delivery_id, webhook_name, argv, started_at, finished_at, status, stdout, stderr)
+                VALUES (:id, 'delivery-id', 'deploy', '[]', datetime(), datetime(), :status, '', '')
+                ",
+                named_params! {":id": id, ":status": status},
+            )
+            .unwrap();
+        }
+
+        web::Data::new(Mutex::new(conn))
+    }
+
+    fn get_config(api_token: Option<&str>) -> web::Data<SharedConfig> {
+        let mut config = Config::default();
+        config.api_token = api_token.map(String::from);
+
+        web::Data::new(SharedConfig::new(config))
+    }
+
+    macro_rules! get_app {
+        ($config:expr) => {
+            init_service(
+                App::new()
+                    .app_data($config)
+                    .app_data(get_database())
+                    .route("/api/executions", web::get().to(list_executions))
+                    .route("/api/executions/{id}", web::get().to(get_execution))
+                    .route("/api/deliveries/{id}", web::get().to(get_delivery)),
+            )
+            .await
+        };
+    }
+
+    fn get_request(uri: &str) -> TestRequest {
+        TestRequest::get()
+            .uri(uri)
+            .insert_header((header::AUTHORIZATION, "Bearer secret-token"))
+    }
+
+    #[actix_web::test]
+    async fn test_authorization() {
+        let app = get_app!(get_config(Some("secret-token")));
+
+        let response = call_service(&app, get_request("/api/executions").to_request()).await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let request = TestRequest::get().uri("/api/executions").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+        let request = TestRequest::get()
+            .uri("/api/executions")
+            .insert_header((header::AUTHORIZATION, "Bearer wrong-token"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+        let app = get_app!(get_config(None));
+        let response = call_service(&app, get_request("/api/executions").to_request()).await;
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+    }
+
+    #[actix_web::test]
+    async fn test_list_executions() {
+        let app = get_app!(get_config(Some("secret-token")));
+
+        let request = get_request("/api/executions?status=failed&limit=1").to_request();
+        let body: serde_json::Value = read_body_json(call_service(&app, request).await).await;
+        assert_eq!("3", body["executions"][0]["id"]);
+        assert_eq!(1, body["executions"].as_array().unwrap().len());
+        assert_eq!(1, body["next_offset"]);
+
+        let request = get_request("/api/executions?status=failed&limit=1&offset=1").to_request();
+        let body: serde_json::Value = read_body_json(call_service(&app, request).await).await;
+        assert_eq!("2", body["executions"][0]["id"]);
+        assert!(body["next_offset"].is_null());
+
+        let request = get_request("/api/executions?since=yesterday").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+
+    #[actix_web::test]
+    async fn test_get_execution_and_delivery() {
+        let app = get_app!(get_config(Some("secret-token")));
+
+        let request = get_request("/api/executions/2").to_request();
+        let body: serde_json::Value = read_body_json(call_service(&app, request).await).await;
+        assert_eq!("failure", body["status"]);
+        assert_eq!("delivery-id", body["delivery_id"]);
+
+        let request = get_request("/api/executions/unknown").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+        let request = get_request("/api/deliveries/delivery-id").to_request();
+        let body: serde_json::Value = read_body_json(call_service(&app, request).await).await;
+        assert_eq!("deploy", body["delivery"]["matched_webhooks"][0]);
+        assert_eq!(
+            serde_json::json!([
+                ["x-gitlab-event", "Push Hook"],
+                ["x-gitlab-token", "[REDACTED]"]
+            ]),
+            body["delivery"]["headers"]
+        );
+        assert_eq!(3, body["executions"].as_array().unwrap().len());
+    }
+
+    #[actix_web::test]
+    async fn test_replay_delivery() {
+        let mut config = Config::default();
+        config.api_token = Some("secret-token".to_string());
+        config.webhooks = serde_json::from_str(
+            r#"[{
+                "name": "deploy",
+                "matchers": [{"match-json-body": {"ref": "refs/heads/main"}}],
+                "actions-to-execute": ["make", "deploy"]
+            }]"#,
+        )
+        .unwrap();
+        let (sender, mut receiver) = mpsc::channel::<Vec<Job>>(8);
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .app_data(web::Data::new(sender))
+                .app_data(get_database())
+                .route(
+                    "/api/deliveries/{id}/replay",
+                    web::post().to(replay_delivery),
+                ),
+        )
+        .await;
+
+        let request = TestRequest::post()
+            .uri("/api/deliveries/delivery-id/replay")
+            .insert_header((header::AUTHORIZATION, "Bearer secret-token"))
+            .to_request();
+        let body: serde_json::Value = read_body_json(call_service(&app, request).await).await;
+        assert_eq!("delivery-id", body["replay_of"]);
+        assert_eq!("deploy", body["matching_webhooks"][0]);
+
+        let jobs = receiver.recv().await.unwrap();
+        assert_eq!(body["delivery_id"], jobs[0].request.id);
+        assert_eq!(r#"{"ref": "refs/heads/main"}"#, jobs[0].request.body);
+
+        let request = TestRequest::post()
+            .uri("/api/deliveries/unknown/replay")
+            .insert_header((header::AUTHORIZATION, "Bearer secret-token"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+    }
+}
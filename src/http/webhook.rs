@@ -1,16 +1,26 @@
 use crate::actions::matching_webhooks;
-use crate::config::Config;
+use crate::actions::request::IncomingRequest;
+use crate::actions::request::Job;
+use crate::actions::signature_verification;
+use crate::actions::signature_verification::RejectedWebhook;
+use crate::config::SharedConfig;
 use crate::config::Webhook;
+use crate::db::deliveries;
 use actix_web::web;
 use actix_web::HttpRequest;
 use actix_web::HttpResponse;
+use rusqlite::named_params;
+use rusqlite::Connection;
+use std::sync::Arc;
+use std::sync::Mutex;
 use tokio::sync::mpsc;
 
 pub(crate) async fn webhook(
     request: HttpRequest,
     body_bytes: web::Bytes,
-    config: web::Data<Config>,
-    queue_sender: web::Data<mpsc::Sender<Vec<Webhook>>>,
+    config: web::Data<SharedConfig>,
+    queue_sender: web::Data<mpsc::Sender<Vec<Job>>>,
+    database: web::Data<Mutex<Connection>>,
 ) -> HttpResponse {
     let body_as_string = String::from_utf8(body_bytes.to_vec());
     if body_as_string.is_err() {
@@ -20,52 +30,114 @@ pub(crate) async fn webhook(
 
     let headers = request.headers();
 
-    let config = config.get_ref();
+    let config = config.get();
 
-    let matching_webhooks = matching_webhooks::from_request_parts(config, &body_as_string, headers);
-    if matching_webhooks.is_err() {
-        return HttpResponse::BadRequest()
-            .body("Could not get actions to execute from this request.");
+    // Signatures are only verified for the webhooks the request is aimed at.
+    let matching_webhooks =
+        matching_webhooks::from_request_parts(&config, &body_as_string, headers);
+    let rejected_webhooks =
+        signature_verification::rejected_webhooks(&matching_webhooks, headers, &body_bytes);
+    if !rejected_webhooks.is_empty() && rejected_webhooks.len() == matching_webhooks.len() {
+        return unauthorized(&rejected_webhooks, &database);
     }
-    let matching_webhooks = matching_webhooks.unwrap();
 
-    if !matching_webhooks.is_empty() {
-        let mut matching_webhooks_names = Vec::new();
+    let matching_webhooks: Vec<Webhook> = matching_webhooks
+        .into_iter()
+        .filter(|webhook| {
+            !rejected_webhooks
+                .iter()
+                .any(|rejected| rejected.webhook_name == webhook.name)
+        })
+        .collect();
+
+    let source_ip = request.peer_addr().map(|address| address.ip().to_string());
+    let incoming_request = Arc::new(IncomingRequest::new(body_as_string, headers, source_ip));
+    let jobs: Vec<Job> = matching_webhooks
+        .into_iter()
+        .map(|webhook| Job::new(Arc::clone(&incoming_request), webhook))
+        .collect();
+
+    // Requests that matched no webhook are stored too, so that they can be inspected.
+    let insert_response = deliveries::insert_delivery(
+        &mut database
+            .lock()
+            .expect("Could not retrieve database connection."),
+        &incoming_request,
+        &jobs,
+    );
+
+    if let Err(err) = insert_response {
+        error!("Could not store request: {:?}", err);
+        return HttpResponse::InternalServerError().body("Could not store request.");
+    }
 
-        for webhook in &matching_webhooks {
-            matching_webhooks_names.push(webhook.name.clone());
-        }
+    if jobs.is_empty() {
+        return HttpResponse::BadRequest().body(format!(
+            "Request matched no webhook.\nBody:\n{}\n",
+            incoming_request.body
+        ));
+    }
 
-        let response_body = serde_json::json!({
-            "matching_webhooks": matching_webhooks_names.as_slice(),
-        });
+    let matching_webhooks_names: Vec<String> =
+        jobs.iter().map(|job| job.webhook.name.clone()).collect();
 
-        let sender_response = queue_sender.send(matching_webhooks).await;
+    let response_body = serde_json::json!({
+        "matching_webhooks": matching_webhooks_names.as_slice(),
+    });
 
-        if sender_response.is_err() {
-            error!(
-                "Could not send message to queue: {:?}",
-                sender_response.unwrap_err()
-            );
-            return HttpResponse::InternalServerError().body("Could not send message to queue.");
-        }
+    let sender_response = queue_sender.send(jobs).await;
+
+    if let Err(err) = sender_response {
+        error!("Could not send message to queue: {:?}", err);
+        return HttpResponse::InternalServerError().body("Could not send message to queue.");
+    }
 
-        sender_response.unwrap();
+    HttpResponse::Ok()
+        .append_header(("Content-Type", "application/json"))
+        .body(response_body.to_string())
+}
+
+fn unauthorized(
+    rejected_webhooks: &[RejectedWebhook],
+    database: &web::Data<Mutex<Connection>>,
+) -> HttpResponse {
+    warn!("Rejected request with invalid signature.");
+
+    let conn = database
+        .lock()
+        .expect("Could not retrieve database connection.");
+
+    for rejected in rejected_webhooks {
+        let res = conn.execute(
+            "
+            INSERT INTO logs_rejected_requests (
+                rejection_date,
+                webhook_name,
+                rejection_reason
+            ) VALUES (
+                datetime(),
+                :webhook_name,
+                :rejection_reason
+            )
+            ",
+            named_params! {
+                ":webhook_name": rejected.webhook_name,
+                ":rejection_reason": rejected.reason,
+            },
+        );
 
-        return HttpResponse::Ok()
-            .append_header(("Content-Type", "application/json"))
-            .body(response_body.to_string());
+        if let Err(err) = res {
+            error!("Could not log rejected request: {}", err);
+        }
     }
 
-    HttpResponse::BadRequest().body(format!(
-        "Request matched no webhook.\nBody:\n{}\n",
-        body_as_string
-    ))
+    HttpResponse::Unauthorized().body("Invalid signature.")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Config;
     use crate::test_utils;
     use actix_web::dev::ServiceResponse;
     use actix_web::http;
@@ -86,10 +158,11 @@ mod tests {
 
         let (sender, _) = mpsc::channel(8);
 
-        let config = web::Data::new(Config::default());
+        let config = web::Data::new(SharedConfig::new(Config::default()));
         let queue_sender = web::Data::new(sender);
+        let database = web::Data::new(Mutex::new(test_utils::get_test_database_connection()));
 
-        let res = webhook(req.clone(), body_webhook, config, queue_sender).await;
+        let res = webhook(req.clone(), body_webhook, config, queue_sender, database).await;
 
         assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
 
@@ -106,20 +179,21 @@ mod tests {
         let body_str =
             r#"{"repository":{"url":"https://github.com/my-org/my-repo"},"action":"published"}"#
                 .as_bytes();
-        let body_webhook = web::Bytes::from_static(body_str.clone());
+        let body_webhook = web::Bytes::from_static(body_str);
 
         let req = TestRequest::default()
             .uri("http://127.0.0.1:8000/webhook")
-            .set_payload(body_str.clone())
+            .set_payload(body_str)
             .to_http_request();
 
         let (sender, mut receiver) = mpsc::channel(8);
 
         let config = test_utils::get_sample_config().unwrap();
-        let config = web::Data::new(config);
+        let config = web::Data::new(SharedConfig::new(config));
         let queue_sender = web::Data::new(sender);
+        let database = web::Data::new(Mutex::new(test_utils::get_test_database_connection()));
 
-        let res = webhook(req, body_webhook, config, queue_sender).await;
+        let res = webhook(req, body_webhook, config, queue_sender, database).await;
 
         assert_eq!(res.status(), http::StatusCode::OK);
 
@@ -128,8 +202,12 @@ mod tests {
         let res = res.unwrap();
 
         assert_eq!(2, res.len());
-        assert_eq!("my_webhook_name", res[0].name);
-        assert_eq!("my_webhook_name_2", res[1].name);
+        assert_eq!("my_webhook_name", res[0].webhook.name);
+        assert_eq!("my_webhook_name_2", res[1].webhook.name);
+        assert_eq!(
+            r#"{"repository":{"url":"https://github.com/my-org/my-repo"},"action":"published"}"#,
+            res[0].request.body
+        );
     }
 
     #[actix_web::test]
@@ -143,10 +221,11 @@ mod tests {
         let (sender, mut receiver) = mpsc::channel(8);
 
         let config = test_utils::get_sample_config().unwrap();
-        let config = web::Data::new(config);
+        let config = web::Data::new(SharedConfig::new(config));
         let queue_sender = web::Data::new(sender);
+        let database = web::Data::new(Mutex::new(test_utils::get_test_database_connection()));
 
-        let res = webhook(req, web::Bytes::new(), config, queue_sender).await;
+        let res = webhook(req, web::Bytes::new(), config, queue_sender, database).await;
 
         assert_eq!(res.status(), http::StatusCode::OK);
 
@@ -155,7 +234,11 @@ mod tests {
         let res = res.unwrap();
 
         assert_eq!(1, res.len());
-        assert_eq!("my_webhook_name", res[0].name);
+        assert_eq!("my_webhook_name", res[0].webhook.name);
+        assert!(res[0]
+            .request
+            .headers
+            .contains(&("x-github-event".to_string(), "release".to_string())));
     }
 
     #[actix_web::test]
@@ -174,10 +257,11 @@ mod tests {
         let (sender, _) = mpsc::channel(8);
 
         let config = test_utils::get_sample_config().unwrap();
-        let config = web::Data::new(config);
+        let config = web::Data::new(SharedConfig::new(config));
         let queue_sender = web::Data::new(sender);
+        let database = web::Data::new(Mutex::new(test_utils::get_test_database_connection()));
 
-        let res = webhook(req.clone(), request_body, config, queue_sender).await;
+        let res = webhook(req.clone(), request_body, config, queue_sender, database).await;
 
         assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
 
@@ -185,4 +269,150 @@ mod tests {
 
         assert_eq!(body_str, "Invalid body.");
     }
+
+    fn get_config_with_secret() -> Config {
+        serde_json::from_str(
+            r#"{
+                "webhooks": [{
+                    "name": "signed_webhook",
+                    "secret": "It's a Secret to Everybody",
+                    "matchers": [{"match-headers": {"x-github-event": "push"}}],
+                    "actions-to-execute": ["echo", "signed"]
+                }]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_webhook_with_valid_signature() {
+        let body_str = "Hello, World!".as_bytes();
+
+        let req = TestRequest::default()
+            .uri("http://127.0.0.1:8000/webhook")
+            .insert_header(("X-GitHub-Event", "push"))
+            .insert_header((
+                "X-Hub-Signature-256",
+                "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+            ))
+            .set_payload(body_str)
+            .to_http_request();
+
+        let (sender, mut receiver) = mpsc::channel(8);
+
+        let config = web::Data::new(SharedConfig::new(get_config_with_secret()));
+        let queue_sender = web::Data::new(sender);
+        let database = web::Data::new(Mutex::new(test_utils::get_test_database_connection()));
+
+        let res = webhook(
+            req,
+            web::Bytes::from_static(body_str),
+            config,
+            queue_sender,
+            database,
+        )
+        .await;
+
+        assert_eq!(res.status(), http::StatusCode::OK);
+
+        let res = receiver.recv().await.unwrap();
+        assert_eq!(1, res.len());
+        assert_eq!("signed_webhook", res[0].webhook.name);
+    }
+
+    #[actix_web::test]
+    async fn test_webhook_with_invalid_signature() {
+        let body_str = "Forged payload".as_bytes();
+
+        let req = TestRequest::default()
+            .uri("http://127.0.0.1:8000/webhook")
+            .insert_header(("X-GitHub-Event", "push"))
+            .insert_header((
+                "X-Hub-Signature-256",
+                "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+            ))
+            .set_payload(body_str)
+            .to_http_request();
+
+        let (sender, _) = mpsc::channel(8);
+
+        let config = web::Data::new(SharedConfig::new(get_config_with_secret()));
+        let queue_sender = web::Data::new(sender);
+        let database = web::Data::new(Mutex::new(test_utils::get_test_database_connection()));
+
+        let res = webhook(
+            req,
+            web::Bytes::from_static(body_str),
+            config,
+            queue_sender,
+            database.clone(),
+        )
+        .await;
+
+        assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+
+        let number_of_rejections: i64 = database
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM logs_rejected_requests", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(1, number_of_rejections);
+    }
+
+    #[actix_web::test]
+    async fn test_only_matching_webhooks_verify_signatures() {
+        let mut config = get_config_with_secret();
+        config.webhooks.extend(
+            serde_json::from_str::<Vec<Webhook>>(
+                r#"[{
+                    "name": "other_signed_webhook",
+                    "secret": "Another secret",
+                    "matchers": [{"match-headers": {"x-github-event": "release"}}],
+                    "actions-to-execute": ["echo", "other"]
+                }]"#,
+            )
+            .unwrap(),
+        );
+        let config = web::Data::new(SharedConfig::new(config));
+        let database = web::Data::new(Mutex::new(test_utils::get_test_database_connection()));
+        let body_str = "Hello, World!".as_bytes();
+
+        for (event, status) in [
+            ("push", http::StatusCode::OK),
+            ("issues", http::StatusCode::BAD_REQUEST),
+        ] {
+            let req = TestRequest::default()
+                .uri("http://127.0.0.1:8000/webhook")
+                .insert_header(("X-GitHub-Event", event))
+                .insert_header((
+                    "X-Hub-Signature-256",
+                    "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+                ))
+                .set_payload(body_str)
+                .to_http_request();
+            let (sender, _receiver) = mpsc::channel(8);
+
+            let res = webhook(
+                req,
+                web::Bytes::from_static(body_str),
+                config.clone(),
+                web::Data::new(sender),
+                database.clone(),
+            )
+            .await;
+
+            assert_eq!(status, res.status());
+        }
+
+        let number_of_rejections: i64 = database
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM logs_rejected_requests", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(0, number_of_rejections);
+    }
 }
@@ -1,11 +1,19 @@
+use crate::config::Secret;
 use crate::APPLICATION_NAME;
 use log::Level;
 use pretty_env_logger::env_logger::fmt::Color;
 use pretty_env_logger::env_logger::fmt::Style;
 use pretty_env_logger::env_logger::fmt::StyledValue;
+use std::borrow::Cow;
 use std::io::Write;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
+use std::sync::RwLock;
+
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+/// Values of the config resolved from environment variables and secret files.
+static SECRETS: RwLock<Vec<Secret>> = RwLock::new(Vec::new());
 
 pub fn set_verbosity_value(value: u8, is_quiet: bool) {
     let env_var_name = format!("{}_LOG", APPLICATION_NAME.to_ascii_uppercase());
@@ -50,12 +58,51 @@ pub fn set_verbosity_value(value: u8, is_quiet: bool) {
 
             let time = f.timestamp_millis();
 
-            writeln!(f, " {} {}{} > {}", time, level, target, record.args(),)
+            writeln!(
+                f,
+                " {} {}{} > {}",
+                time,
+                level,
+                target,
+                redact(&record.args().to_string()),
+            )
         })
         .try_init()
         .unwrap();
 }
 
+/// Secrets are hidden from the logs, and from the output of the actions stored in the database.
+/// The list is replaced on each load of the config, so the secrets removed from it are forgotten.
+pub(crate) fn set_secrets(mut secrets: Vec<Secret>) {
+    secrets.sort_by(|a, b| a.value.cmp(&b.value));
+    secrets.dedup_by(|a, b| a.value == b.value);
+    // Longer secrets are replaced first, in case they contain shorter ones.
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.value.len()));
+
+    *SECRETS.write().expect("Could not register secrets.") = secrets;
+}
+
+pub(crate) fn secrets() -> Vec<Secret> {
+    SECRETS.read().expect("Could not read secrets.").clone()
+}
+
+pub(crate) fn redact(message: &str) -> Cow<'_, str> {
+    let secrets = SECRETS.read().expect("Could not read secrets.");
+
+    redact_secrets(message, &secrets)
+}
+
+fn redact_secrets<'a>(message: &'a str, secrets: &[Secret]) -> Cow<'a, str> {
+    let mut message = Cow::Borrowed(message);
+    for secret in secrets {
+        if message.contains(secret.value.as_str()) {
+            message = Cow::Owned(message.replace(secret.value.as_str(), REDACTED));
+        }
+    }
+
+    message
+}
+
 // This struct is a copy/paste of the one in pertty_env_logger.
 // It's necessary for left-padding the message type.
 struct Padded<T> {
@@ -90,3 +137,31 @@ fn colored_level<'a>(style: &'a mut Style, level: Level) -> StyledValue<'a, &'st
         Level::Error => style.set_color(Color::Red).value("ERROR"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let secrets: Vec<Secret> = ["pagoo-test-secret-longer", "pagoo-test-secret"]
+            .into_iter()
+            .map(|value| Secret {
+                value: value.to_string(),
+                placeholder: "${PAGOO_TEST_SECRET}".to_string(),
+            })
+            .collect();
+
+        assert_eq!(
+            "token=[REDACTED] other=[REDACTED]",
+            redact_secrets(
+                "token=pagoo-test-secret-longer other=pagoo-test-secret",
+                &secrets
+            )
+        );
+        assert!(matches!(
+            redact_secrets("nothing to hide", &secrets),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -2,7 +2,7 @@ extern crate pretty_env_logger;
 #[macro_use]
 extern crate log;
 
-use crate::commands::{init, serve_webhook};
+use crate::commands::{init, logs, replay, serve_webhook, test, validate};
 use clap::Arg;
 use clap::ArgAction;
 use clap::ArgMatches;
@@ -15,18 +15,27 @@ use std::process::Termination;
 mod actions {
     pub(crate) mod executor;
     pub(crate) mod matching_webhooks;
+    pub(crate) mod replay;
+    pub(crate) mod request;
+    pub(crate) mod signature_verification;
+    pub(crate) mod templating;
 }
 
 mod config;
 
 mod commands {
     pub(crate) mod init;
+    pub(crate) mod logs;
+    pub(crate) mod replay;
     pub(crate) mod serve_webhook;
+    pub(crate) mod test;
+    pub(crate) mod validate;
 }
 
 mod db;
 
 mod http {
+    pub(crate) mod api;
     pub(crate) mod webhook;
 }
 
@@ -35,10 +44,13 @@ mod logging;
 mod matchers {
     pub(crate) mod headers;
     pub(crate) mod json;
+    pub(crate) mod json_path;
 }
 
 mod serve;
 
+mod signatures;
+
 #[cfg(test)]
 mod test_utils;
 
@@ -141,7 +153,14 @@ impl CommandHandler {
 
 fn application_commands() -> CommandList {
     CommandList {
-        commands: vec![serve_webhook::get_command(), init::get_command()],
+        commands: vec![
+            serve_webhook::get_command(),
+            init::get_command(),
+            logs::get_command(),
+            replay::get_command(),
+            test::get_command(),
+            validate::get_command(),
+        ],
     }
 }
 
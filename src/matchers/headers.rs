@@ -1,39 +1,50 @@
-use crate::config::Matcher;
+use crate::config::HeaderValueMatcher;
 use actix_web::http::header::HeaderMap;
 
-pub(crate) fn match_headers(headers: &HeaderMap, matcher: &Matcher) -> Result<bool, anyhow::Error> {
-    if matcher.match_headers.is_none() {
-        return Ok(false);
-    }
-
-    let matcher_headers = matcher.match_headers.as_ref().unwrap();
-    let number_of_headers = matcher_headers.len();
-    let mut headers_matching = 0;
+/// Returns the reason why the header does not match, if it does not.
+pub(crate) fn check_header(
+    headers: &HeaderMap,
+    header_name: &str,
+    header_value_matcher: &HeaderValueMatcher,
+) -> Result<(), String> {
+    let header_value_as_string = match headers.get(header_name) {
+        Some(header_value) => Some(
+            header_value
+                .to_str()
+                .map_err(|_| "its value is not valid text".to_string())?,
+        ),
+        None => None,
+    };
 
-    for (header_name, header_value) in matcher_headers {
-        if headers.contains_key(header_name) {
-            let header_value_as_string = headers
-                .get(header_name)
-                .ok_or_else(|| {
-                    anyhow::anyhow!("Could not get header by name \"{}\".", header_name)
-                })?
-                .to_str()?;
-            if header_value_as_string == header_value {
-                headers_matching += 1
-            }
-        }
+    if header_value_matcher.matches(header_value_as_string) {
+        return Ok(());
     }
 
-    Ok(headers_matching == number_of_headers)
+    match header_value_as_string {
+        Some(value) => Err(format!("it is \"{}\"", value)),
+        None => Err("it is absent".to_string()),
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
+    use crate::config::HeaderValueMatcher;
+    use crate::config::Matcher;
     use actix_web::http::header::HeaderName;
     use actix_web::http::header::HeaderValue;
     use std::collections::HashMap;
 
+    fn match_headers(headers: &HeaderMap, matcher: &Matcher) -> bool {
+        matcher
+            .match_headers
+            .as_ref()
+            .unwrap()
+            .iter()
+            .all(|(name, value_matcher)| check_header(headers, name, value_matcher).is_ok())
+    }
+
     #[test]
     fn test_match_headers_with_different_case() {
         let mut headers = HeaderMap::new();
@@ -48,13 +59,19 @@ mod tests {
 
         let matcher = Matcher {
             match_headers: Some(HashMap::from([
-                ("X-HEADER-1".to_string(), "value1".to_string()),
-                ("X-HEADER-2".to_string(), "value2".to_string()),
+                (
+                    "X-HEADER-1".to_string(),
+                    HeaderValueMatcher::Equals("value1".to_string()),
+                ),
+                (
+                    "X-HEADER-2".to_string(),
+                    HeaderValueMatcher::Equals("value2".to_string()),
+                ),
             ])),
-            match_json_body: None,
+            ..Default::default()
         };
 
-        assert_eq!(match_headers(&headers, &matcher).unwrap(), true);
+        assert_eq!(match_headers(&headers, &matcher), true);
     }
 
     #[test]
@@ -71,12 +88,44 @@ mod tests {
 
         let matcher = Matcher {
             match_headers: Some(HashMap::from([
-                ("X-Wrong-HEADER-1".to_string(), "value1".to_string()),
-                ("X-Wrong-HEADER-2".to_string(), "value2".to_string()),
+                (
+                    "X-Wrong-HEADER-1".to_string(),
+                    HeaderValueMatcher::Equals("value1".to_string()),
+                ),
+                (
+                    "X-Wrong-HEADER-2".to_string(),
+                    HeaderValueMatcher::Equals("value2".to_string()),
+                ),
             ])),
-            match_json_body: None,
+            ..Default::default()
         };
 
-        assert_eq!(match_headers(&headers, &matcher).unwrap(), false);
+        assert_eq!(match_headers(&headers, &matcher), false);
+    }
+
+    #[test]
+    fn test_match_headers_with_value_matchers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("x-github-event"),
+            HeaderValue::from_static("pull_request"),
+        );
+        headers.insert(
+            HeaderName::from_static("user-agent"),
+            HeaderValue::from_static("GitHub-Hookshot/044aadd"),
+        );
+
+        let matcher: Matcher = serde_json::from_str(
+            r#"{
+                "match-headers": {
+                    "x-github-event": {"regex": "^(push|pull_request)$"},
+                    "user-agent": {"glob": "GitHub-Hookshot/*"},
+                    "x-github-delivery": {"exists": false}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(match_headers(&headers, &matcher));
     }
 }
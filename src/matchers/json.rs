@@ -1,60 +1,92 @@
+use crate::config::JsonArrayMode;
+use crate::config::JsonCompareMode;
 use crate::config::Matcher;
+use serde_json::Value;
 
-pub(crate) fn match_json(body_as_string: &str, matcher: &Matcher) -> Result<bool, anyhow::Error> {
-    if matcher.match_json_body.is_none() {
-        return Ok(false);
-    }
+/// Returns the reason why the JSON body does not match, if it does not.
+pub(crate) fn check_json(deserialized_json: &Value, matcher: &Matcher) -> Result<(), String> {
+    let match_json_body = match &matcher.match_json_body {
+        Some(match_json_body) => match_json_body,
+        None => return Err("no JSON body is expected".to_string()),
+    };
 
-    let match_json_body = matcher
-        .match_json_body
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("No match_json_body"))?;
-    let match_json_body = serde_json::json!(match_json_body);
-
-    let deserialized_result = serde_json::from_str::<serde_json::Value>(body_as_string);
-    if deserialized_result.is_err() {
-        debug!("Deserialization failed, skipping JSON matcher.");
-        debug!(
-            "Deserialization error: {}",
-            deserialized_result.unwrap_err()
-        );
-        return Ok(false);
+    match (matcher.json_compare_mode, matcher.json_array_mode) {
+        (JsonCompareMode::Strict, _) => assert_json_diff::assert_json_matches_no_panic(
+            deserialized_json,
+            match_json_body,
+            assert_json_diff::Config::new(assert_json_diff::CompareMode::Strict),
+        ),
+        (JsonCompareMode::Inclusive, JsonArrayMode::Prefix) => {
+            assert_json_diff::assert_json_matches_no_panic(
+                deserialized_json,
+                match_json_body,
+                assert_json_diff::Config::new(assert_json_diff::CompareMode::Inclusive),
+            )
+        }
+        (JsonCompareMode::Inclusive, JsonArrayMode::Any) => {
+            json_includes(deserialized_json, match_json_body, "")
+        }
     }
+}
 
-    let deserialized_json = deserialized_result?;
-
-    let json_comparator_config =
-        assert_json_diff::Config::new(assert_json_diff::CompareMode::Strict);
-    let matching_json_result = assert_json_diff::assert_json_matches_no_panic(
-        &deserialized_json,
-        &match_json_body,
-        json_comparator_config,
-    );
-
-    if matching_json_result.is_ok() {
-        return Ok(true);
+/// Checks that "expected" is a subset of "actual".
+/// Each element of an expected array must be included in at least one element of the actual array.
+fn json_includes(actual: &Value, expected: &Value, path: &str) -> Result<(), String> {
+    match (actual, expected) {
+        (Value::Object(actual_map), Value::Object(expected_map)) => {
+            for (key, expected_value) in expected_map {
+                let key_path = format!("{}.{}", path, key);
+                match actual_map.get(key) {
+                    Some(actual_value) => json_includes(actual_value, expected_value, &key_path)?,
+                    None => return Err(format!("key \"{}\" is missing", key_path)),
+                }
+            }
+            Ok(())
+        }
+        (Value::Array(actual_array), Value::Array(expected_array)) => {
+            for (index, expected_value) in expected_array.iter().enumerate() {
+                let is_included = actual_array
+                    .iter()
+                    .any(|actual_value| json_includes(actual_value, expected_value, path).is_ok());
+                if !is_included {
+                    return Err(format!(
+                        "no element of \"{}\" includes expected element at index {}: {}",
+                        path, index, expected_value
+                    ));
+                }
+            }
+            Ok(())
+        }
+        (actual, expected) if actual == expected => Ok(()),
+        (actual, expected) => Err(format!(
+            "\"{}\" is {} but {} was expected",
+            path, actual, expected
+        )),
     }
-
-    Ok(false)
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
+    fn match_json(body_as_string: &str, matcher: &Matcher) -> bool {
+        check_json(&serde_json::from_str(body_as_string).unwrap(), matcher).is_ok()
+    }
+
     #[test]
     fn test_match_json() {
         let body_as_string = "{\"key1\": \"value1\", \"key2\": \"value2\"}".to_string();
 
         let matcher = Matcher {
-            match_headers: None,
             match_json_body: Some(serde_json::json!({
                 "key1": "value1",
                 "key2": "value2",
             })),
+            ..Default::default()
         };
 
-        assert_eq!(match_json(&body_as_string, &matcher).unwrap(), true);
+        assert_eq!(match_json(&body_as_string, &matcher), true);
     }
 
     #[test]
@@ -62,13 +94,101 @@ mod tests {
         let body_as_string = "{\"key1\": \"value1\", \"key2\": \"value2\"}".to_string();
 
         let matcher = Matcher {
-            match_headers: None,
             match_json_body: Some(serde_json::json!({
                 "wrongKey1": "value1",
                 "wrongKey2": "value2",
             })),
+            ..Default::default()
         };
 
-        assert_eq!(match_json(&body_as_string, &matcher).unwrap(), false);
+        assert_eq!(match_json(&body_as_string, &matcher), false);
+    }
+
+    fn get_github_payload(event: &str) -> String {
+        let path = format!(
+            "{}/samples/github/{}_payload.json",
+            env!("CARGO_MANIFEST_DIR"),
+            event
+        );
+
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn get_matcher(matcher: serde_json::Value) -> Matcher {
+        serde_json::from_value(matcher).unwrap()
+    }
+
+    #[test]
+    fn test_inclusive_mode_is_default() {
+        let matcher = get_matcher(serde_json::json!({
+            "match-json-body": {
+                "ref": "refs/heads/main",
+                "repository": {"full_name": "my-org/my-repo"}
+            }
+        }));
+
+        assert_eq!(JsonCompareMode::Inclusive, matcher.json_compare_mode);
+        assert!(match_json(&get_github_payload("push"), &matcher));
+    }
+
+    #[test]
+    fn test_strict_mode_with_real_payload() {
+        let matcher = get_matcher(serde_json::json!({
+            "json-compare-mode": "strict",
+            "match-json-body": {
+                "ref": "refs/heads/main",
+                "repository": {"full_name": "my-org/my-repo"}
+            }
+        }));
+
+        assert!(!match_json(&get_github_payload("push"), &matcher));
+    }
+
+    #[test]
+    fn test_inclusive_mode_with_different_value() {
+        let matcher = get_matcher(serde_json::json!({
+            "match-json-body": {
+                "action": "published",
+                "release": {"prerelease": true}
+            }
+        }));
+
+        assert!(!match_json(&get_github_payload("release"), &matcher));
+    }
+
+    #[test]
+    fn test_any_array_mode() {
+        let matcher = get_matcher(serde_json::json!({
+            "match-json-body": {
+                "commits": [
+                    {"modified": ["deploy/production.sh"]},
+                    {"author": {"username": "octocat"}}
+                ]
+            }
+        }));
+
+        assert_eq!(JsonArrayMode::Any, matcher.json_array_mode);
+        assert!(match_json(&get_github_payload("push"), &matcher));
+    }
+
+    #[test]
+    fn test_prefix_array_mode() {
+        let payload = get_github_payload("push");
+
+        let matcher = get_matcher(serde_json::json!({
+            "json-array-mode": "prefix",
+            "match-json-body": {
+                "commits": [{"author": {"username": "octocat"}}]
+            }
+        }));
+        assert!(match_json(&payload, &matcher));
+
+        let matcher = get_matcher(serde_json::json!({
+            "json-array-mode": "prefix",
+            "match-json-body": {
+                "commits": [{"modified": ["deploy/production.sh"]}]
+            }
+        }));
+        assert!(!match_json(&payload, &matcher));
     }
 }
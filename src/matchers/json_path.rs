@@ -0,0 +1,141 @@
+use crate::config::JsonPathCondition;
+use crate::config::JsonPathOperator;
+use serde_json::Value;
+
+/// Returns the reason why the condition failed, if it did.
+pub(crate) fn check_condition(
+    document: &Value,
+    condition: &JsonPathCondition,
+) -> Result<(), String> {
+    let values = condition.path.resolve(document);
+
+    if let JsonPathOperator::Exists(should_exist) = condition.operator {
+        return match (values.is_empty(), should_exist) {
+            (false, true) | (true, false) => Ok(()),
+            (true, true) => Err("it resolved to no value".to_string()),
+            (false, false) => Err(format!("it resolved to {}", values[0])),
+        };
+    }
+
+    if values.is_empty() {
+        return Err("it resolved to no value".to_string());
+    }
+
+    if values
+        .iter()
+        .any(|value| value_matches(value, &condition.operator))
+    {
+        return Ok(());
+    }
+
+    let resolved: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+
+    Err(format!("it resolved to {}", resolved.join(", ")))
+}
+
+fn value_matches(value: &Value, operator: &JsonPathOperator) -> bool {
+    match operator {
+        JsonPathOperator::Equals(expected) => value == expected,
+        JsonPathOperator::Regex(regex) => value.as_str().is_some_and(|s| regex.is_match(s)),
+        JsonPathOperator::In(expected_values) => expected_values.contains(value),
+        JsonPathOperator::Exists(_) => true,
+        JsonPathOperator::GreaterThan(number) => value.as_f64().is_some_and(|v| v > *number),
+        JsonPathOperator::LowerThan(number) => value.as_f64().is_some_and(|v| v < *number),
+        JsonPathOperator::Contains(expected) => match (value, expected) {
+            (Value::String(string), Value::String(substring)) => {
+                string.contains(substring.as_str())
+            }
+            (Value::Array(array), expected) => array.contains(expected),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Matcher;
+
+    const BODY: &str = r#"{
+        "ref": "refs/tags/v1.2.0",
+        "size": 3,
+        "milestone": null,
+        "labels": ["deploy", "urgent"],
+        "commits": [
+            {"modified": ["src/main.rs"]},
+            {"modified": ["deploy/script.sh"]}
+        ]
+    }"#;
+
+    fn match_json_path(body_as_string: &str, matcher: &Matcher) -> bool {
+        let document: Value = serde_json::from_str(body_as_string).unwrap();
+
+        matcher
+            .match_json_path
+            .as_ref()
+            .unwrap()
+            .iter()
+            .all(|condition| check_condition(&document, condition).is_ok())
+    }
+
+    fn get_matcher(conditions: &str) -> Matcher {
+        serde_json::from_str(&format!(r#"{{"match-json-path": {}}}"#, conditions)).unwrap()
+    }
+
+    #[test]
+    fn test_match_json_path() {
+        let matcher = get_matcher(
+            r#"[
+                {"path": "$.ref", "regex": "^refs/tags/"},
+                {"path": "$.commits[*].modified[*]", "contains": "deploy/"},
+                {"path": "/labels", "contains": "urgent"},
+                {"path": "$.size", "gt": 2},
+                {"path": "$.size", "lt": 4},
+                {"path": "$.ref", "in": ["refs/tags/v1.2.0", "refs/tags/v1.3.0"]},
+                {"path": "$.commits[0].modified[0]", "equals": "src/main.rs"},
+                {"path": "$.pusher", "exists": false},
+                {"path": "$.milestone", "equals": null}
+            ]"#,
+        );
+
+        assert!(match_json_path(BODY, &matcher));
+    }
+
+    #[test]
+    fn test_not_match_json_path() {
+        let matcher = get_matcher(
+            r#"[
+                {"path": "$.ref", "regex": "^refs/tags/"},
+                {"path": "$.ref", "equals": "refs/heads/main"}
+            ]"#,
+        );
+
+        assert!(!match_json_path(BODY, &matcher));
+    }
+
+    #[test]
+    fn test_equals_null() {
+        let matcher = get_matcher(r#"[{"path": "$.ref", "equals": null}]"#);
+        assert!(!match_json_path(BODY, &matcher));
+
+        let matcher = get_matcher(r#"[{"path": "$.pusher", "equals": null}]"#);
+        assert!(!match_json_path(BODY, &matcher));
+    }
+
+    #[test]
+    fn test_failure_reason() {
+        let document: Value = serde_json::from_str(BODY).unwrap();
+        let matcher =
+            get_matcher(r#"[{"path": "$.size", "gt": 5}, {"path": "$.nope", "exists": true}]"#);
+        let conditions = matcher.match_json_path.unwrap();
+
+        assert_eq!(
+            check_condition(&document, &conditions[0]).unwrap_err(),
+            "it resolved to 3"
+        );
+        assert_eq!(
+            check_condition(&document, &conditions[1]).unwrap_err(),
+            "it resolved to no value"
+        );
+    }
+}
@@ -1,13 +1,16 @@
-use crate::actions::executor;
+mod reload;
+pub(crate) mod workers;
+
 use crate::config;
-use crate::config::Webhook;
+use crate::config::SharedConfig;
 use crate::db::get_database_connection;
+use crate::db::jobs;
+use crate::db::lock_workers;
+use crate::http::api;
 use actix_web::web;
 use actix_web::App;
 use actix_web::HttpServer;
-use rusqlite::Connection;
 use std::io::Error;
-use std::io::ErrorKind;
 use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 
@@ -39,22 +42,58 @@ pub(crate) async fn serve(
             config_file_path.unwrap().to_str().unwrap()
         );
         let err = config.unwrap_err();
-        return Err(Error::new(ErrorKind::Other, err));
+        return Err(Error::other(err));
     }
 
     let config = config.unwrap();
     let database_file = config.database_file.clone();
 
-    let database_connection = get_database_connection(database_file).unwrap();
+    let database_connection = get_database_connection(database_file.clone()).unwrap();
+
+    // Held until the server stops, so that commands queue their jobs on this server.
+    let _workers_lock = match lock_workers(database_file).map_err(Error::other)? {
+        Some(lock) => Some(lock),
+        None => {
+            warn!("Another process is executing the jobs of this database.");
+            None
+        }
+    };
+
+    let interrupted_jobs =
+        jobs::requeue_interrupted_jobs(&database_connection).map_err(Error::other)?;
+    if interrupted_jobs > 0 {
+        warn!(
+            "{} jobs were interrupted by the last shutdown, they will be executed again.",
+            interrupted_jobs
+        );
+    }
+    let queued_jobs = jobs::get_queued_jobs(&database_connection).map_err(Error::other)?;
+    if !queued_jobs.is_empty() {
+        info!("Resuming {} queued jobs.", queued_jobs.len());
+    }
+    let database_connection = Arc::new(Mutex::new(database_connection));
 
     let (sender, receiver) = mpsc::channel(8);
 
     info!("Starting queue workers...");
 
-    start_workers(receiver, database_connection);
-
-    let config = web::Data::new(config);
+    workers::start_workers(
+        receiver,
+        Arc::clone(&database_connection),
+        config.workers.unwrap_or(workers::DEFAULT_WORKERS),
+        queued_jobs,
+    );
+
+    let config = web::Data::new(SharedConfig::new(config));
+
+    // The resolved path is watched, so that the same file is reloaded
+    // even if another default config file is created.
+    let config_file_path = config::get_config_file(config_file).map_err(Error::other)?;
+    reload::watch(config_file_path.clone(), config.clone());
+    #[cfg(unix)]
+    reload::reload_on_hangup(config_file_path, config.clone());
     let transmitter_data = web::Data::new(sender);
+    let database_data = web::Data::from(database_connection);
 
     info!("Starting HTTP server on {}:{}", host, port);
 
@@ -62,23 +101,20 @@ pub(crate) async fn serve(
         App::new()
             .app_data(config.clone())
             .app_data(transmitter_data.clone())
+            .app_data(database_data.clone())
             .service(web::resource(API_PATH).to(crate::http::webhook::webhook))
+            .service(
+                web::scope(api::API_PREFIX)
+                    .route("/executions", web::get().to(api::list_executions))
+                    .route("/executions/{id}", web::get().to(api::get_execution))
+                    .route("/deliveries/{id}", web::get().to(api::get_delivery))
+                    .route(
+                        "/deliveries/{id}/replay",
+                        web::post().to(api::replay_delivery),
+                    ),
+            )
     })
     .bind((host, port_as_int))?
     .run()
     .await
 }
-
-fn start_workers(mut receiver: mpsc::Receiver<Vec<Webhook>>, conn: Connection) {
-    tokio::spawn(async move {
-        let conn = Arc::new(Mutex::new(conn));
-
-        while let Some(webhooks) = receiver.recv().await {
-            let res = executor::execute_webhook_actions(webhooks, Arc::clone(&conn));
-
-            if res.is_err() {
-                error!("Error executing actions");
-            }
-        }
-    });
-}
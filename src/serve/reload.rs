@@ -0,0 +1,164 @@
+use crate::config;
+use crate::config::Config;
+use crate::config::SharedConfig;
+use actix_web::web;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Validates and loads the config file again, and replaces the active config with it.
+/// The active config is kept when the file is invalid.
+pub(crate) fn reload(config_file: &Path, shared_config: &SharedConfig) -> anyhow::Result<()> {
+    let config_file = config_file.to_str().unwrap();
+
+    let config = config::get_config(Some(config_file))
+        .map_err(|err| anyhow::anyhow!("{}\nThe current config is kept.", err))?;
+
+    warn_about_restart_options(&shared_config.get(), &config);
+    let webhooks = config.webhooks.len();
+    shared_config.replace(config);
+
+    info!(
+        "Reloaded config file \"{}\" with {} webhook(s).",
+        config_file, webhooks
+    );
+
+    Ok(())
+}
+
+/// These options are used when the server starts, changing them has no effect until a restart.
+fn warn_about_restart_options(current: &Config, new: &Config) {
+    if current.workers != new.workers {
+        warn!("The \"workers\" option changed, restart the server to apply it.");
+    }
+    if current.database_file != new.database_file {
+        warn!("The \"database_file\" option changed, restart the server to apply it.");
+    }
+}
+
+/// Reloads the config when the modification date of the file or of an included file changes,
+/// or when files are added to or removed from the included ones.
+/// Polling also detects editors that replace the file instead of writing to it.
+pub(crate) fn watch(config_file: PathBuf, shared_config: web::Data<SharedConfig>) {
+    std::thread::spawn(move || {
+        let mut last_modified = modification_dates(&config_file, &shared_config.get());
+
+        loop {
+            std::thread::sleep(WATCH_POLL_INTERVAL);
+
+            let modified = modification_dates(&config_file, &shared_config.get());
+            if modified[0].1.is_none() || modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            info!(
+                "Config file \"{}\" changed, reloading it.",
+                config_file.display()
+            );
+            if let Err(err) = reload(&config_file, &shared_config) {
+                error!("Could not reload the config: {}", err);
+            }
+        }
+    });
+}
+
+/// The config file comes first, followed by the files included by the active config.
+fn modification_dates(config_file: &Path, config: &Config) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let included_files = config::included_files(config_file, &config.include).files;
+
+    std::iter::once(config_file.to_path_buf())
+        .chain(included_files)
+        .map(|file| {
+            let modified = modification_date(&file);
+            (file, modified)
+        })
+        .collect()
+}
+
+fn modification_date(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Reloads the config when the process receives a SIGHUP signal.
+#[cfg(unix)]
+pub(crate) fn reload_on_hangup(config_file: PathBuf, shared_config: web::Data<SharedConfig>) {
+    use tokio::signal::unix::signal;
+    use tokio::signal::unix::SignalKind;
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(err) => {
+            error!("Could not listen to the SIGHUP signal: {}", err);
+            return;
+        }
+    };
+
+    actix_web::rt::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!("Received SIGHUP, reloading the config.");
+            if let Err(err) = reload(&config_file, &shared_config) {
+                error!("Could not reload the config: {}", err);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn webhook(name: &str) -> String {
+        format!(
+            r#"{{"name": "{}", "match": {{"match-headers": {{"x-event": "push"}}}}, "actions-to-execute": ["true"]}}"#,
+            name
+        )
+    }
+
+    #[test]
+    fn test_reload() {
+        let config_file = std::env::temp_dir().join(format!("pagoo-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &config_file,
+            format!(r#"{{"webhooks": [{}]}}"#, webhook("first")),
+        )
+        .unwrap();
+        let shared_config =
+            SharedConfig::new(config::get_config(Some(config_file.to_str().unwrap())).unwrap());
+        let active_config = shared_config.get();
+
+        std::fs::write(
+            &config_file,
+            format!(
+                r#"{{"webhooks": [{}, {}]}}"#,
+                webhook("first"),
+                webhook("second")
+            ),
+        )
+        .unwrap();
+        reload(&config_file, &shared_config).unwrap();
+
+        assert_eq!(2, shared_config.get().webhooks.len());
+        // The config used by requests being handled does not change.
+        assert_eq!(1, active_config.webhooks.len());
+
+        std::fs::write(
+            &config_file,
+            format!(
+                r#"{{"webhooks": [{}, {}]}}"#,
+                webhook("first"),
+                webhook("first")
+            ),
+        )
+        .unwrap();
+        assert!(reload(&config_file, &shared_config).is_err());
+        assert_eq!("second", shared_config.get().webhooks[1].name);
+
+        std::fs::remove_file(config_file).unwrap();
+    }
+}
@@ -0,0 +1,557 @@
+use crate::actions::executor;
+use crate::actions::executor::CommandStatus;
+use crate::actions::request::CancellationToken;
+use crate::actions::request::Job;
+use crate::config::ConcurrencyPolicy;
+use crate::db::jobs;
+use crate::db::jobs::JobState;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tokio::sync::Semaphore;
+
+pub(crate) const DEFAULT_WORKERS: usize = 4;
+
+const RETRY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Jobs are stored in the database before they are sent to the workers,
+/// the "queued_jobs" are the ones that were not executed before the last shutdown.
+/// The database is also polled for jobs queued by other processes, like the "replay" command.
+pub(crate) fn start_workers(
+    mut receiver: mpsc::Receiver<Vec<Job>>,
+    conn: Arc<Mutex<Connection>>,
+    workers: usize,
+    queued_jobs: Vec<Job>,
+) {
+    tokio::spawn(async move {
+        let mut pool = WorkerPool::new(workers, conn);
+        let mut poll_interval = actix_web::rt::time::interval(QUEUE_POLL_INTERVAL);
+
+        pool.dispatch(queued_jobs);
+
+        loop {
+            tokio::select! {
+                jobs = receiver.recv() => match jobs {
+                    Some(jobs) => pool.dispatch(jobs),
+                    None => break,
+                },
+                _ = poll_interval.tick() => pool.dispatch_queued_jobs(),
+            }
+        }
+    });
+}
+
+/// Executes the jobs of a delivery outside of the server and waits until they are finished.
+/// They are all started at once, so the concurrency policies of their webhooks are not applied:
+/// callers must hold the workers lock, so that no server executes jobs at the same time.
+/// Returns whether all the jobs succeeded.
+#[actix_web::main]
+pub(crate) async fn run_delivery_jobs(
+    conn: Connection,
+    delivery_id: &str,
+    jobs: Vec<Job>,
+    workers: usize,
+) -> anyhow::Result<bool> {
+    let conn = Arc::new(Mutex::new(conn));
+    let permits = Arc::new(Semaphore::new(workers));
+
+    let handles: Vec<_> = jobs
+        .into_iter()
+        .map(|job| tokio::spawn(run_job(job, Arc::clone(&permits), Arc::clone(&conn))))
+        .collect();
+
+    for handle in handles {
+        if let Err(err) = handle.await {
+            error!("Job was aborted: {}", err);
+        }
+    }
+
+    let states = jobs::get_delivery_job_states(&conn.lock().unwrap(), delivery_id)?;
+    for (webhook_name, state) in &states {
+        info!("Webhook \"{}\": {}", webhook_name, state);
+    }
+
+    Ok(states
+        .iter()
+        .all(|(_, state)| *state == JobState::Succeeded.to_string()))
+}
+
+/// Runs jobs on blocking threads, with at most "workers" jobs running at the same time.
+struct WorkerPool {
+    permits: Arc<Semaphore>,
+    conn: Arc<Mutex<Connection>>,
+    lanes: HashMap<String, Lane>,
+    /// Jobs can be received both from the server and from the database,
+    /// the ones that were dispatched and are still queued must not be dispatched again.
+    dispatched_jobs: HashSet<String>,
+}
+
+/// Executes the jobs of a single webhook one after the other, in the order they were received.
+struct Lane {
+    sender: mpsc::UnboundedSender<Job>,
+    pending_jobs: Arc<Mutex<Vec<(String, CancellationToken)>>>,
+}
+
+impl WorkerPool {
+    fn new(workers: usize, conn: Arc<Mutex<Connection>>) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(workers)),
+            conn,
+            lanes: HashMap::new(),
+            dispatched_jobs: HashSet::new(),
+        }
+    }
+
+    fn dispatch_queued_jobs(&mut self) {
+        let queued_jobs = match jobs::get_queued_jobs(&self.conn.lock().unwrap()) {
+            Ok(queued_jobs) => queued_jobs,
+            Err(err) => {
+                error!("Could not get the queued jobs: {}", err);
+                return;
+            }
+        };
+
+        // The other ones were claimed by a worker, or cancelled.
+        self.dispatched_jobs
+            .retain(|id| queued_jobs.iter().any(|job| job.id == *id));
+
+        self.dispatch(queued_jobs);
+    }
+
+    fn dispatch(&mut self, jobs: Vec<Job>) {
+        for job in jobs {
+            if !self.is_new(&job) {
+                continue;
+            }
+            self.dispatched_jobs.insert(job.id.clone());
+
+            let policy = job.webhook.concurrency;
+
+            if policy == ConcurrencyPolicy::Parallel {
+                tokio::spawn(run_job(
+                    job,
+                    Arc::clone(&self.permits),
+                    Arc::clone(&self.conn),
+                ));
+                continue;
+            }
+
+            let permits = &self.permits;
+            let conn = &self.conn;
+            let lane = self
+                .lanes
+                .entry(job.webhook.name.clone())
+                .or_insert_with(|| Lane::start(Arc::clone(permits), Arc::clone(conn)));
+
+            match policy {
+                ConcurrencyPolicy::SkipIfRunning if lane.is_busy() => {
+                    info!(
+                        "Webhook {} is already running, skipping request \"{}\".",
+                        job.webhook.label(),
+                        job.request.id
+                    );
+                    let conn = self.conn.lock().unwrap();
+                    if let Err(err) = jobs::finish_job(&conn, &job.id, JobState::Cancelled) {
+                        error!("Could not update job \"{}\": {}", job.id, err);
+                    }
+                    continue;
+                }
+                ConcurrencyPolicy::CancelPrevious => lane.cancel_pending_jobs(),
+                _ => {}
+            }
+
+            lane.push(job);
+        }
+    }
+
+    /// Jobs that were already dispatched, claimed or cancelled are not dispatched again.
+    fn is_new(&self, job: &Job) -> bool {
+        if self.dispatched_jobs.contains(&job.id) {
+            return false;
+        }
+
+        match jobs::is_queued(&self.conn.lock().unwrap(), &job.id) {
+            Ok(is_queued) => is_queued,
+            Err(err) => {
+                error!("Could not get the state of job \"{}\": {}", job.id, err);
+                false
+            }
+        }
+    }
+}
+
+impl Lane {
+    fn start(permits: Arc<Semaphore>, conn: Arc<Mutex<Connection>>) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Job>();
+        let pending_jobs: Arc<Mutex<Vec<(String, CancellationToken)>>> = Arc::default();
+
+        let lane_pending_jobs = Arc::clone(&pending_jobs);
+        tokio::spawn(async move {
+            while let Some(job) = receiver.recv().await {
+                let job_id = job.id.clone();
+
+                run_job(job, Arc::clone(&permits), Arc::clone(&conn)).await;
+
+                lane_pending_jobs
+                    .lock()
+                    .unwrap()
+                    .retain(|(id, _)| *id != job_id);
+            }
+        });
+
+        Self {
+            sender,
+            pending_jobs,
+        }
+    }
+
+    fn is_busy(&self) -> bool {
+        !self.pending_jobs.lock().unwrap().is_empty()
+    }
+
+    fn cancel_pending_jobs(&self) {
+        for (_, cancellation) in self.pending_jobs.lock().unwrap().iter() {
+            cancellation.cancel();
+        }
+    }
+
+    fn push(&self, job: Job) {
+        self.pending_jobs
+            .lock()
+            .unwrap()
+            .push((job.id.clone(), job.cancellation.clone()));
+
+        if self.sender.send(job).is_err() {
+            error!("Could not send job to its queue.");
+        }
+    }
+}
+
+async fn run_job(job: Job, permits: Arc<Semaphore>, conn: Arc<Mutex<Connection>>) {
+    let job = Arc::new(job);
+    let mut attempt = 1;
+
+    loop {
+        let permit = permits
+            .acquire()
+            .await
+            .expect("The workers semaphore is never closed.");
+
+        let attempt_job = Arc::clone(&job);
+        let attempt_conn = Arc::clone(&conn);
+        let res = tokio::task::spawn_blocking(move || {
+            execute_attempt(&attempt_job, attempt, &attempt_conn)
+        })
+        .await;
+
+        // Workers are not used while waiting for the next attempt.
+        drop(permit);
+
+        let retry_delay = match res {
+            Ok(Ok(Some(retry_delay))) => retry_delay,
+            Ok(Ok(None)) => return,
+            Ok(Err(err)) => {
+                error!("Error executing actions: {}", err);
+                return;
+            }
+            Err(err) => {
+                // The job would otherwise stay "running" until the next restart.
+                error!("Job \"{}\" was aborted: {}", job.id, err);
+                if let Err(err) = jobs::finish_job(&conn.lock().unwrap(), &job.id, JobState::Failed)
+                {
+                    error!("Could not update job \"{}\": {}", job.id, err);
+                }
+                return;
+            }
+        };
+
+        wait_for_retry(&job, retry_delay).await;
+        attempt += 1;
+    }
+}
+
+/// Executes an attempt of the job and returns the delay before the next one,
+/// if the job must be retried.
+fn execute_attempt(
+    job: &Job,
+    attempt: u32,
+    conn: &Mutex<Connection>,
+) -> anyhow::Result<Option<Duration>> {
+    if attempt == 1 && !jobs::claim_job(&conn.lock().unwrap(), &job.id)? {
+        debug!("Job \"{}\" was already claimed, skipping it.", job.id);
+        return Ok(None);
+    }
+
+    let (state, exit_code) = match executor::execute_job(job, attempt, conn) {
+        Ok(result) => {
+            let state = match result.status {
+                CommandStatus::Success => JobState::Succeeded,
+                CommandStatus::Cancelled => JobState::Cancelled,
+                CommandStatus::Failure
+                | CommandStatus::Signaled
+                | CommandStatus::SpawnError
+                | CommandStatus::TimedOut => JobState::Failed,
+            };
+            (state, result.exit_code)
+        }
+        Err(err) => {
+            error!("Error executing job \"{}\": {}", job.id, err);
+            (JobState::Failed, None)
+        }
+    };
+
+    if state == JobState::Failed {
+        if let Some(retry) = &job.webhook.retry {
+            if retry.should_retry(attempt, exit_code) {
+                let retry_delay = retry.delay(attempt);
+                warn!(
+                    "Attempt {}/{} of webhook {} failed, retrying in {} seconds.",
+                    attempt,
+                    retry.max_attempts,
+                    job.webhook.label(),
+                    retry_delay.as_secs_f64()
+                );
+                return Ok(Some(retry_delay));
+            }
+            warn!(
+                "Attempt {}/{} of webhook {} failed.",
+                attempt,
+                retry.max_attempts,
+                job.webhook.label()
+            );
+        }
+    }
+
+    jobs::finish_job(&conn.lock().unwrap(), &job.id, state)?;
+
+    Ok(None)
+}
+
+/// Stops waiting when the job is cancelled, the next attempt then marks it as cancelled.
+async fn wait_for_retry(job: &Job, retry_delay: Duration) {
+    let deadline = Instant::now() + retry_delay;
+
+    while Instant::now() < deadline && !job.cancellation.is_cancelled() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        actix_web::rt::time::sleep(remaining.min(RETRY_POLL_INTERVAL)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::request::IncomingRequest;
+    use crate::config::Webhook;
+    use crate::db::deliveries;
+    use crate::test_utils;
+
+    fn get_jobs(conn: &Mutex<Connection>, policy: &str, command: &str) -> Vec<Job> {
+        let webhook: Webhook = serde_json::from_str(&format!(
+            r#"{{
+                "name": "deploy",
+                "concurrency": "{}",
+                "matchers": [],
+                "actions-to-execute": {{"command": "{}", "shell": "sh -c"}}
+            }}"#,
+            policy, command
+        ))
+        .unwrap();
+
+        let request = IncomingRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            ..Default::default()
+        };
+        let jobs = vec![Job::new(Arc::new(request), webhook)];
+        deliveries::insert_delivery(&mut conn.lock().unwrap(), &jobs[0].request, &jobs).unwrap();
+
+        jobs
+    }
+
+    async fn wait_for_statuses(conn: &Mutex<Connection>, expected: usize) -> Vec<String> {
+        for _ in 0..100 {
+            let statuses: Vec<String> = {
+                let conn = conn.lock().unwrap();
+                let mut statement = conn
+                    .prepare("SELECT status FROM executions ORDER BY rowid")
+                    .unwrap();
+                let statuses = statement
+                    .query_map([], |row| row.get(0))
+                    .unwrap()
+                    .map(|status| status.unwrap())
+                    .collect();
+                statuses
+            };
+            if statuses.len() >= expected {
+                return statuses;
+            }
+            actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        panic!("Jobs were not executed in time.");
+    }
+
+    #[actix_web::test]
+    async fn test_serial_jobs_do_not_overlap() {
+        let conn = Arc::new(Mutex::new(test_utils::get_test_database_connection()));
+        let mut pool = WorkerPool::new(4, Arc::clone(&conn));
+        let lock_file = std::env::temp_dir().join(format!("pagoo-{}.lock", uuid::Uuid::new_v4()));
+        let command = format!(
+            "mkdir {0} || exit 1; sleep 0.2; rmdir {0}",
+            lock_file.display()
+        );
+
+        pool.dispatch(get_jobs(&conn, "serial", &command));
+        pool.dispatch(get_jobs(&conn, "serial", &command));
+
+        let statuses = wait_for_statuses(&conn, 2).await;
+        assert_eq!(vec!["success", "success"], statuses);
+    }
+
+    #[actix_web::test]
+    async fn test_skip_if_running() {
+        let conn = Arc::new(Mutex::new(test_utils::get_test_database_connection()));
+        let mut pool = WorkerPool::new(4, Arc::clone(&conn));
+
+        pool.dispatch(get_jobs(&conn, "skip-if-running", "sleep 0.2"));
+        pool.dispatch(get_jobs(&conn, "skip-if-running", "sleep 0.2"));
+
+        let statuses = wait_for_statuses(&conn, 1).await;
+        actix_web::rt::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(vec!["success"], statuses);
+        assert_eq!(1, wait_for_statuses(&conn, 1).await.len());
+    }
+
+    #[actix_web::test]
+    async fn test_cancel_previous() {
+        let conn = Arc::new(Mutex::new(test_utils::get_test_database_connection()));
+        let mut pool = WorkerPool::new(4, Arc::clone(&conn));
+
+        pool.dispatch(get_jobs(&conn, "cancel-previous", "sleep 10"));
+        actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+        pool.dispatch(get_jobs(&conn, "cancel-previous", "echo latest"));
+
+        let statuses = wait_for_statuses(&conn, 2).await;
+        assert_eq!(vec!["cancelled", "success"], statuses);
+    }
+
+    #[actix_web::test]
+    async fn test_job_state_is_stored() {
+        let conn = Arc::new(Mutex::new(test_utils::get_test_database_connection()));
+        let mut pool = WorkerPool::new(4, Arc::clone(&conn));
+        let jobs = get_jobs(&conn, "parallel", "exit 1");
+        let job_id = jobs[0].id.clone();
+
+        pool.dispatch(jobs);
+
+        for _ in 0..100 {
+            let state: String = conn
+                .lock()
+                .unwrap()
+                .query_row("SELECT state FROM jobs WHERE id = ?", [&job_id], |row| {
+                    row.get(0)
+                })
+                .unwrap();
+            if state == "failed" {
+                return;
+            }
+            actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        panic!("Job state was not updated in time.");
+    }
+
+    #[actix_web::test]
+    async fn test_requeued_job_with_stale_body_file() {
+        let conn = Arc::new(Mutex::new(test_utils::get_test_database_connection()));
+        let mut pool = WorkerPool::new(4, Arc::clone(&conn));
+        let jobs = get_jobs(&conn, "serial", "cat $PAGOO_BODY_FILE");
+        let job_id = jobs[0].id.clone();
+
+        // The server crashed while the job was running.
+        let queued_jobs = {
+            let conn = conn.lock().unwrap();
+            assert!(jobs::claim_job(&conn, &job_id).unwrap());
+            jobs::requeue_interrupted_jobs(&conn).unwrap();
+            jobs::get_queued_jobs(&conn).unwrap()
+        };
+        let body_file = executor::body_file_path(&job_id);
+        std::fs::write(&body_file, "stale body").unwrap();
+
+        pool.dispatch(queued_jobs);
+
+        assert_eq!(vec!["success"], wait_for_statuses(&conn, 1).await);
+        for _ in 0..100 {
+            if !body_file.exists() {
+                return;
+            }
+            actix_web::rt::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        panic!("The body file was not removed.");
+    }
+
+    #[actix_web::test]
+    async fn test_jobs_queued_by_other_processes() {
+        let conn = Arc::new(Mutex::new(test_utils::get_test_database_connection()));
+        let mut pool = WorkerPool::new(4, Arc::clone(&conn));
+        let jobs = get_jobs(&conn, "cancel-previous", "sleep 0.2");
+
+        // The job received by the server is found in the database too,
+        // it must not be dispatched again, which would cancel it.
+        pool.dispatch(jobs);
+        pool.dispatch_queued_jobs();
+        assert_eq!(vec!["success"], wait_for_statuses(&conn, 1).await);
+
+        // A job queued by the "replay" command.
+        get_jobs(&conn, "cancel-previous", "echo replayed");
+        pool.dispatch_queued_jobs();
+
+        let statuses = wait_for_statuses(&conn, 2).await;
+        assert_eq!(vec!["success", "success"], statuses);
+    }
+
+    #[actix_web::test]
+    async fn test_failed_attempts_are_retried() {
+        let conn = Arc::new(Mutex::new(test_utils::get_test_database_connection()));
+        let mut pool = WorkerPool::new(4, Arc::clone(&conn));
+        let counter_file =
+            std::env::temp_dir().join(format!("pagoo-{}.attempts", uuid::Uuid::new_v4()));
+        let mut jobs = get_jobs(
+            &conn,
+            "serial",
+            &format!(
+                "echo attempt >> {0}; [ $(wc -l < {0}) -ge 3 ]",
+                counter_file.display()
+            ),
+        );
+        jobs[0].webhook.retry =
+            Some(serde_json::from_str(r#"{"max-attempts": 5, "initial-delay": 0}"#).unwrap());
+
+        pool.dispatch(jobs);
+
+        let statuses = wait_for_statuses(&conn, 3).await;
+        let _ = std::fs::remove_file(counter_file);
+        assert_eq!(vec!["failure", "failure", "success"], statuses);
+
+        let attempts: Vec<(i64, i64)> = {
+            let conn = conn.lock().unwrap();
+            let mut statement = conn
+                .prepare("SELECT attempt, max_attempts FROM executions ORDER BY rowid")
+                .unwrap();
+            let attempts = statement
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap()
+                .map(|attempt| attempt.unwrap())
+                .collect();
+            attempts
+        };
+        assert_eq!(vec![(1, 5), (2, 5), (3, 5)], attempts);
+    }
+}
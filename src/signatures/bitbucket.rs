@@ -0,0 +1,77 @@
+use crate::signatures::get_header;
+use crate::signatures::verify_hmac_sha256;
+use crate::signatures::SignatureVerifier;
+use actix_web::http::header::HeaderMap;
+
+pub(crate) const SIGNATURE_HEADER: &str = "x-hub-signature";
+
+/// Bitbucket Server (formerly Stash) signs payloads with HMAC-SHA256,
+/// using the same header name as GitHub's legacy SHA-1 signatures.
+pub(crate) struct BitbucketVerifier {
+    secret: String,
+}
+
+impl BitbucketVerifier {
+    pub(crate) fn new(secret: String) -> Self {
+        Self { secret }
+    }
+}
+
+impl SignatureVerifier for BitbucketVerifier {
+    fn verify(&self, headers: &HeaderMap, body_bytes: &[u8]) -> Result<(), anyhow::Error> {
+        let signature = get_header(headers, SIGNATURE_HEADER)?;
+        let signature = signature
+            .strip_prefix("sha256=")
+            .ok_or_else(|| anyhow::anyhow!("Signature must start with \"sha256=\"."))?;
+
+        verify_hmac_sha256(&self.secret, body_bytes, signature)
+            .map_err(|err| anyhow::anyhow!("Invalid \"{}\" header: {}", SIGNATURE_HEADER, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signatures::tests::get_sample_payload;
+    use crate::signatures::tests::headers_with;
+    use crate::signatures::tests::SAMPLE_SECRET;
+
+    const SAMPLE_PAYLOAD_SIGNATURE: &str =
+        "sha256=e0ba1c1fc9a391a7ac0d09b64aba590205f03eecf1ca20d4932a431827f66050";
+
+    #[test]
+    fn test_valid_signature() {
+        let headers = headers_with(SIGNATURE_HEADER, SAMPLE_PAYLOAD_SIGNATURE);
+        let verifier = BitbucketVerifier::new(SAMPLE_SECRET.to_string());
+
+        assert!(verifier
+            .verify(&headers, &get_sample_payload("bitbucket"))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_invalid_signature() {
+        let headers = headers_with(SIGNATURE_HEADER, SAMPLE_PAYLOAD_SIGNATURE);
+        let verifier = BitbucketVerifier::new("wrong secret".to_string());
+
+        let result = verifier.verify(&headers, &get_sample_payload("bitbucket"));
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid \"x-hub-signature\" header: Signature does not match."
+        );
+    }
+
+    #[test]
+    fn test_legacy_sha1_signature_is_refused() {
+        let headers = headers_with(
+            SIGNATURE_HEADER,
+            "sha1=01dc10d0c83e72ed246219cdd91669667fe2ca59",
+        );
+        let verifier = BitbucketVerifier::new(SAMPLE_SECRET.to_string());
+
+        assert!(verifier
+            .verify(&headers, &get_sample_payload("bitbucket"))
+            .is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use crate::signatures::get_header;
+use crate::signatures::verify_hmac_sha256;
+use crate::signatures::SignatureVerifier;
+use actix_web::http::header::HeaderMap;
+
+pub(crate) const SIGNATURE_HEADER: &str = "x-gitea-signature";
+
+pub(crate) struct GiteaVerifier {
+    secret: String,
+}
+
+impl GiteaVerifier {
+    pub(crate) fn new(secret: String) -> Self {
+        Self { secret }
+    }
+}
+
+impl SignatureVerifier for GiteaVerifier {
+    fn verify(&self, headers: &HeaderMap, body_bytes: &[u8]) -> Result<(), anyhow::Error> {
+        let signature = get_header(headers, SIGNATURE_HEADER)?;
+
+        verify_hmac_sha256(&self.secret, body_bytes, signature)
+            .map_err(|err| anyhow::anyhow!("Invalid \"{}\" header: {}", SIGNATURE_HEADER, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signatures::tests::get_sample_payload;
+    use crate::signatures::tests::headers_with;
+    use crate::signatures::tests::SAMPLE_SECRET;
+
+    const SAMPLE_PAYLOAD_SIGNATURE: &str =
+        "9631ddec9762a7b348fd01be320a431da3dbeea32c239578b997f9b6792be517";
+
+    #[test]
+    fn test_valid_signature() {
+        let headers = headers_with(SIGNATURE_HEADER, SAMPLE_PAYLOAD_SIGNATURE);
+        let verifier = GiteaVerifier::new(SAMPLE_SECRET.to_string());
+
+        assert!(verifier
+            .verify(&headers, &get_sample_payload("gitea"))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_invalid_signature() {
+        let headers = headers_with(SIGNATURE_HEADER, SAMPLE_PAYLOAD_SIGNATURE);
+        let verifier = GiteaVerifier::new("wrong secret".to_string());
+
+        let result = verifier.verify(&headers, &get_sample_payload("gitea"));
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid \"x-gitea-signature\" header: Signature does not match."
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use crate::signatures::verify_hmac_sha256;
+use crate::signatures::SignatureVerifier;
+use actix_web::http::header::HeaderMap;
+use hmac::Hmac;
+use hmac::Mac;
+use sha1::Sha1;
+
+pub(crate) const SIGNATURE_256_HEADER: &str = "x-hub-signature-256";
+pub(crate) const SIGNATURE_SHA1_HEADER: &str = "x-hub-signature";
+
+pub(crate) struct GithubVerifier {
+    secret: String,
+}
+
+impl GithubVerifier {
+    pub(crate) fn new(secret: String) -> Self {
+        Self { secret }
+    }
+}
+
+impl SignatureVerifier for GithubVerifier {
+    fn verify(&self, headers: &HeaderMap, body_bytes: &[u8]) -> Result<(), anyhow::Error> {
+        if let Some(signature) = headers.get(SIGNATURE_256_HEADER) {
+            let signature = strip_signature_prefix(signature.to_str()?, "sha256=")?;
+
+            return verify_hmac_sha256(&self.secret, body_bytes, signature).map_err(|err| {
+                anyhow::anyhow!("Invalid \"{}\" header: {}", SIGNATURE_256_HEADER, err)
+            });
+        }
+
+        if let Some(signature) = headers.get(SIGNATURE_SHA1_HEADER) {
+            let signature = strip_signature_prefix(signature.to_str()?, "sha1=")?;
+            let signature = hex::decode(signature)?;
+
+            let mut mac = Hmac::<Sha1>::new_from_slice(self.secret.as_bytes())?;
+            mac.update(body_bytes);
+
+            return mac.verify_slice(&signature).map_err(|_| {
+                anyhow::anyhow!(
+                    "Invalid \"{}\" header: Signature does not match.",
+                    SIGNATURE_SHA1_HEADER
+                )
+            });
+        }
+
+        Err(anyhow::anyhow!(
+            "Request has no \"{}\" or \"{}\" header.",
+            SIGNATURE_256_HEADER,
+            SIGNATURE_SHA1_HEADER
+        ))
+    }
+}
+
+fn strip_signature_prefix<'a>(
+    header_value: &'a str,
+    prefix: &str,
+) -> Result<&'a str, anyhow::Error> {
+    header_value
+        .strip_prefix(prefix)
+        .ok_or_else(|| anyhow::anyhow!("Signature must start with \"{}\".", prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signatures::tests::get_sample_payload;
+    use crate::signatures::tests::headers_with;
+    use crate::signatures::tests::SAMPLE_SECRET;
+
+    // Values from GitHub's documentation about validating webhook deliveries.
+    const SECRET: &str = "It's a Secret to Everybody";
+    const PAYLOAD: &str = "Hello, World!";
+    const SHA256_SIGNATURE: &str =
+        "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+    const SHA1_SIGNATURE: &str = "sha1=01dc10d0c83e72ed246219cdd91669667fe2ca59";
+
+    const SAMPLE_PAYLOAD_SIGNATURE: &str =
+        "sha256=75af2cc4f4978cdc81a6b8e58c131ade6ef03a9bb0e870da79965fb74a951dd2";
+
+    #[test]
+    fn test_valid_sha256_signature() {
+        let headers = headers_with(SIGNATURE_256_HEADER, SHA256_SIGNATURE);
+        let verifier = GithubVerifier::new(SECRET.to_string());
+
+        assert!(verifier.verify(&headers, PAYLOAD.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_valid_sha1_signature() {
+        let headers = headers_with(SIGNATURE_SHA1_HEADER, SHA1_SIGNATURE);
+        let verifier = GithubVerifier::new(SECRET.to_string());
+
+        assert!(verifier.verify(&headers, PAYLOAD.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_valid_signature_with_sample_payload() {
+        let headers = headers_with(SIGNATURE_256_HEADER, SAMPLE_PAYLOAD_SIGNATURE);
+        let verifier = GithubVerifier::new(SAMPLE_SECRET.to_string());
+
+        assert!(verifier
+            .verify(&headers, &get_sample_payload("github"))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_invalid_signature() {
+        let headers = headers_with(SIGNATURE_256_HEADER, SHA256_SIGNATURE);
+        let verifier = GithubVerifier::new("wrong secret".to_string());
+
+        let result = verifier.verify(&headers, PAYLOAD.as_bytes());
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid \"x-hub-signature-256\" header: Signature does not match."
+        );
+    }
+
+    #[test]
+    fn test_missing_signature() {
+        let verifier = GithubVerifier::new(SECRET.to_string());
+
+        assert!(verifier
+            .verify(&HeaderMap::new(), PAYLOAD.as_bytes())
+            .is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::signatures::get_header;
+use crate::signatures::SignatureVerifier;
+use actix_web::http::header::HeaderMap;
+use subtle::ConstantTimeEq;
+
+pub(crate) const TOKEN_HEADER: &str = "x-gitlab-token";
+
+/// GitLab does not sign its payloads,
+/// it sends the configured secret token as-is in a header.
+pub(crate) struct GitlabVerifier {
+    secret: String,
+}
+
+impl GitlabVerifier {
+    pub(crate) fn new(secret: String) -> Self {
+        Self { secret }
+    }
+}
+
+impl SignatureVerifier for GitlabVerifier {
+    fn verify(&self, headers: &HeaderMap, _body_bytes: &[u8]) -> Result<(), anyhow::Error> {
+        let token = get_header(headers, TOKEN_HEADER)?;
+
+        if bool::from(token.as_bytes().ct_eq(self.secret.as_bytes())) {
+            return Ok(());
+        }
+
+        Err(anyhow::anyhow!(
+            "Invalid \"{}\" header: Token does not match.",
+            TOKEN_HEADER
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signatures::tests::get_sample_payload;
+    use crate::signatures::tests::headers_with;
+    use crate::signatures::tests::SAMPLE_SECRET;
+
+    #[test]
+    fn test_valid_token() {
+        let headers = headers_with(TOKEN_HEADER, SAMPLE_SECRET);
+        let verifier = GitlabVerifier::new(SAMPLE_SECRET.to_string());
+
+        assert!(verifier
+            .verify(&headers, &get_sample_payload("gitlab"))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_invalid_token() {
+        let headers = headers_with(TOKEN_HEADER, "wrong-token");
+        let verifier = GitlabVerifier::new(SAMPLE_SECRET.to_string());
+
+        let result = verifier.verify(&headers, &get_sample_payload("gitlab"));
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid \"x-gitlab-token\" header: Token does not match."
+        );
+    }
+
+    #[test]
+    fn test_missing_token() {
+        let verifier = GitlabVerifier::new(SAMPLE_SECRET.to_string());
+
+        let result = verifier.verify(&HeaderMap::new(), &get_sample_payload("gitlab"));
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Request has no \"x-gitlab-token\" header."
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use crate::config::Verification;
+use crate::config::VerificationProvider;
+use actix_web::http::header::HeaderMap;
+use hmac::Hmac;
+use hmac::Mac;
+use sha2::Sha256;
+
+pub(crate) mod bitbucket;
+pub(crate) mod gitea;
+pub(crate) mod github;
+pub(crate) mod gitlab;
+pub(crate) mod stripe;
+
+pub(crate) trait SignatureVerifier {
+    fn verify(&self, headers: &HeaderMap, body_bytes: &[u8]) -> Result<(), anyhow::Error>;
+}
+
+pub(crate) fn get_verifier(verification: &Verification) -> Box<dyn SignatureVerifier> {
+    let secret = verification.secret.clone();
+
+    match verification.provider {
+        VerificationProvider::Github => Box::new(github::GithubVerifier::new(secret)),
+        VerificationProvider::Gitlab => Box::new(gitlab::GitlabVerifier::new(secret)),
+        VerificationProvider::Gitea => Box::new(gitea::GiteaVerifier::new(secret)),
+        VerificationProvider::Bitbucket => Box::new(bitbucket::BitbucketVerifier::new(secret)),
+        VerificationProvider::Stripe => Box::new(stripe::StripeVerifier::new(
+            secret,
+            verification.timestamp_tolerance,
+        )),
+    }
+}
+
+pub(crate) fn get_header<'a>(
+    headers: &'a HeaderMap,
+    header_name: &str,
+) -> Result<&'a str, anyhow::Error> {
+    let header = headers
+        .get(header_name)
+        .ok_or_else(|| anyhow::anyhow!("Request has no \"{}\" header.", header_name))?;
+
+    Ok(header.to_str()?)
+}
+
+/// Compares an hex-encoded HMAC-SHA256 signature with the one computed from the payload.
+/// The comparison is made in constant time.
+pub(crate) fn verify_hmac_sha256(
+    secret: &str,
+    payload: &[u8],
+    hex_signature: &str,
+) -> Result<(), anyhow::Error> {
+    let signature = hex::decode(hex_signature)?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+    mac.update(payload);
+
+    mac.verify_slice(&signature)
+        .map_err(|_| anyhow::anyhow!("Signature does not match."))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use actix_web::http::header::HeaderMap;
+    use actix_web::http::header::HeaderName;
+    use actix_web::http::header::HeaderValue;
+
+    pub(crate) const SAMPLE_SECRET: &str = "pagoo-sample-secret";
+
+    pub(crate) fn get_sample_payload(provider: &str) -> Vec<u8> {
+        let path = format!(
+            "{}/samples/signatures/{}_payload.json",
+            env!("CARGO_MANIFEST_DIR"),
+            provider
+        );
+
+        std::fs::read(path).unwrap()
+    }
+
+    pub(crate) fn headers_with(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static(name),
+            HeaderValue::from_str(value).unwrap(),
+        );
+        headers
+    }
+}
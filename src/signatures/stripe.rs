@@ -0,0 +1,136 @@
+use crate::signatures::get_header;
+use crate::signatures::verify_hmac_sha256;
+use crate::signatures::SignatureVerifier;
+use actix_web::http::header::HeaderMap;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+pub(crate) const SIGNATURE_HEADER: &str = "stripe-signature";
+
+/// Stripe's own libraries use a 5 minutes tolerance by default.
+pub(crate) const DEFAULT_TIMESTAMP_TOLERANCE: u64 = 300;
+
+pub(crate) struct StripeVerifier {
+    secret: String,
+    timestamp_tolerance: u64,
+}
+
+impl StripeVerifier {
+    pub(crate) fn new(secret: String, timestamp_tolerance: Option<u64>) -> Self {
+        Self {
+            secret,
+            timestamp_tolerance: timestamp_tolerance.unwrap_or(DEFAULT_TIMESTAMP_TOLERANCE),
+        }
+    }
+
+    fn verify_at(
+        &self,
+        headers: &HeaderMap,
+        body_bytes: &[u8],
+        now: u64,
+    ) -> Result<(), anyhow::Error> {
+        let header = get_header(headers, SIGNATURE_HEADER)?;
+
+        let mut timestamp: Option<&str> = None;
+        let mut signatures: Vec<&str> = Vec::new();
+
+        for part in header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", value)) => timestamp = Some(value),
+                Some(("v1", value)) => signatures.push(value),
+                _ => {}
+            }
+        }
+
+        let timestamp = timestamp.ok_or_else(|| {
+            anyhow::anyhow!("Invalid \"{}\" header: No timestamp.", SIGNATURE_HEADER)
+        })?;
+
+        let timestamp_value: u64 = timestamp.parse()?;
+        if now.abs_diff(timestamp_value) > self.timestamp_tolerance {
+            return Err(anyhow::anyhow!(
+                "Invalid \"{}\" header: Timestamp is outside the tolerance zone.",
+                SIGNATURE_HEADER
+            ));
+        }
+
+        let mut signed_payload = format!("{}.", timestamp).into_bytes();
+        signed_payload.extend_from_slice(body_bytes);
+
+        if signatures
+            .iter()
+            .any(|signature| verify_hmac_sha256(&self.secret, &signed_payload, signature).is_ok())
+        {
+            return Ok(());
+        }
+
+        Err(anyhow::anyhow!(
+            "Invalid \"{}\" header: No signature matches the payload.",
+            SIGNATURE_HEADER
+        ))
+    }
+}
+
+impl SignatureVerifier for StripeVerifier {
+    fn verify(&self, headers: &HeaderMap, body_bytes: &[u8]) -> Result<(), anyhow::Error> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        self.verify_at(headers, body_bytes, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signatures::tests::get_sample_payload;
+    use crate::signatures::tests::headers_with;
+    use crate::signatures::tests::SAMPLE_SECRET;
+
+    const SAMPLE_TIMESTAMP: u64 = 1664618400;
+    const SAMPLE_PAYLOAD_SIGNATURE: &str =
+        "t=1664618400,v1=041b3fb56b9403912b1a7228b8eb96ff898a9758f5040831923da3c3be69ba71,v0=97b81641d0c9b497d5882a3b76ccfd07c1990fe107c1c3e191d84a356879c760";
+
+    #[test]
+    fn test_valid_signature() {
+        let headers = headers_with(SIGNATURE_HEADER, SAMPLE_PAYLOAD_SIGNATURE);
+        let verifier = StripeVerifier::new(SAMPLE_SECRET.to_string(), None);
+
+        assert!(verifier
+            .verify_at(
+                &headers,
+                &get_sample_payload("stripe"),
+                SAMPLE_TIMESTAMP + 60
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_invalid_signature() {
+        let headers = headers_with(SIGNATURE_HEADER, SAMPLE_PAYLOAD_SIGNATURE);
+        let verifier = StripeVerifier::new("wrong secret".to_string(), None);
+
+        let result = verifier.verify_at(&headers, &get_sample_payload("stripe"), SAMPLE_TIMESTAMP);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid \"stripe-signature\" header: No signature matches the payload."
+        );
+    }
+
+    #[test]
+    fn test_expired_timestamp() {
+        let headers = headers_with(SIGNATURE_HEADER, SAMPLE_PAYLOAD_SIGNATURE);
+        let verifier = StripeVerifier::new(SAMPLE_SECRET.to_string(), Some(30));
+
+        let result = verifier.verify_at(
+            &headers,
+            &get_sample_payload("stripe"),
+            SAMPLE_TIMESTAMP + 60,
+        );
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid \"stripe-signature\" header: Timestamp is outside the tolerance zone."
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use crate::config;
 use crate::config::Config;
+use crate::db;
+use rusqlite::Connection;
 
 #[cfg(test)]
 pub(crate) fn get_sample_config() -> anyhow::Result<Config> {
@@ -9,3 +11,8 @@ pub(crate) fn get_sample_config() -> anyhow::Result<Config> {
 
     config::get_config(Some(path.as_str()))
 }
+
+#[cfg(test)]
+pub(crate) fn get_test_database_connection() -> Connection {
+    db::get_database_connection(Some(":memory:".to_string())).unwrap()
+}